@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Compares the components of links with the names on disk, which may differ in case only on
+/// Windows and macOS but break the build on Linux
+#[derive(Default)]
+pub struct CaseChecker {
+    entries: HashMap<PathBuf, Vec<String>>,
+}
+
+impl CaseChecker {
+    fn names(&mut self, directory: &Path) -> &[String] {
+        self.entries
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| {
+                fs::read_dir(directory)
+                    .map(|entries| {
+                        entries
+                            .filter_map(|entry| entry.ok())
+                            .map(|entry| entry.file_name().to_string_lossy().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+    }
+
+    /// The link spelled as on disk, if it differs from `link` in case only
+    pub fn correct(&mut self, base: &Path, link: &str) -> Option<String> {
+        let mut current = base.to_path_buf();
+        let mut corrected = Vec::new();
+        let mut mismatch = false;
+        for part in link.split('/') {
+            if part.is_empty() || part == "." || part == ".." {
+                current.push(part);
+                corrected.push(part.to_string());
+                continue;
+            }
+            let names = self.names(&current);
+            let name = if names.iter().any(|name| name == part) {
+                part.to_string()
+            } else {
+                let name = names
+                    .iter()
+                    .find(|name| name.to_lowercase() == part.to_lowercase())?
+                    .clone();
+                mismatch = true;
+                name
+            };
+            current.push(&name);
+            corrected.push(name);
+        }
+        mismatch.then(|| corrected.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_mismatch() {
+        let temp = tempfile::tempdir().unwrap();
+        let directory = temp.path();
+        fs::create_dir_all(directory.join("docs/API")).unwrap();
+        fs::create_dir_all(directory.join("docs/assets/en")).unwrap();
+        fs::write(directory.join("docs/assets/en/foo.png"), "").unwrap();
+        let base = directory.join("docs/API");
+
+        let mut checker = CaseChecker::default();
+        assert_eq!(checker.correct(&base, "../assets/en/foo.png"), None);
+        assert_eq!(
+            checker.correct(&base, "../Assets/en/Foo.PNG"),
+            Some("../assets/en/foo.png".to_string())
+        );
+        assert_eq!(checker.correct(&base, "../assets/en/bar.png"), None);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::references::relative_link;
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Changed,
+    Added,
+    Removed,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Changed => "changed",
+            Status::Added => "added",
+            Status::Removed => "removed",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImageChange {
+    /// Path relative to the compared roots
+    pub path: PathBuf,
+    pub status: Status,
+    pub old: Option<PathBuf>,
+    pub new: Option<PathBuf>,
+    /// The diff image, when both images could be decoded
+    pub diff: Option<PathBuf>,
+    pub different_pixels: u64,
+    pub total_pixels: u64,
+}
+
+/// Pairs the files of both trees by relative path and writes a diff image for every changed pair
+pub fn compare_trees(
+    old_files: BTreeMap<PathBuf, PathBuf>,
+    mut new_files: BTreeMap<PathBuf, PathBuf>,
+    output: &Path,
+    tolerance: u8,
+) -> Result<Vec<ImageChange>, anyhow::Error> {
+    let mut changes = Vec::new();
+    for (relative, old) in old_files {
+        let Some(new) = new_files.remove(&relative) else {
+            changes.push(ImageChange {
+                path: relative,
+                status: Status::Removed,
+                old: Some(old),
+                new: None,
+                diff: None,
+                different_pixels: 0,
+                total_pixels: 0,
+            });
+            continue;
+        };
+        if fs::read(&old)? == fs::read(&new)? {
+            continue;
+        }
+        let mut change = ImageChange {
+            path: relative.clone(),
+            status: Status::Changed,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+            diff: None,
+            different_pixels: 0,
+            total_pixels: 0,
+        };
+        if let (Ok(old_image), Ok(new_image)) = (image::open(&old), image::open(&new)) {
+            let (diff, different_pixels) =
+                diff_image(&old_image.to_rgba8(), &new_image.to_rgba8(), tolerance);
+            if different_pixels == 0 {
+                // same pixels, only the encoding changed
+                continue;
+            }
+            // `x.png` and `x.jpg` get their own diff
+            let mut diff_path = output.join("diff").join(&relative).into_os_string();
+            diff_path.push(".png");
+            let diff_path = PathBuf::from(diff_path);
+            if let Some(parent) = diff_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            diff.save(&diff_path)?;
+            change.diff = Some(diff_path);
+            change.different_pixels = different_pixels;
+            change.total_pixels = diff.width() as u64 * diff.height() as u64;
+        }
+        changes.push(change);
+    }
+    for (relative, new) in new_files {
+        changes.push(ImageChange {
+            path: relative,
+            status: Status::Added,
+            old: None,
+            new: Some(new),
+            diff: None,
+            different_pixels: 0,
+            total_pixels: 0,
+        });
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(changes)
+}
+
+/// The new image faded, with the different pixels in red. Areas outside one of the images count as different.
+fn diff_image(old: &RgbaImage, new: &RgbaImage, tolerance: u8) -> (RgbaImage, u64) {
+    let width = old.width().max(new.width());
+    let height = old.height().max(new.height());
+    let mut different_pixels = 0;
+    let diff = RgbaImage::from_fn(width, height, |x, y| {
+        let old_pixel = old.get_pixel_checked(x, y);
+        let new_pixel = new.get_pixel_checked(x, y);
+        match (old_pixel, new_pixel) {
+            (Some(a), Some(b)) if a.0.iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= tolerance) => {
+                let luma = (b.0[0] as u32 * 299 + b.0[1] as u32 * 587 + b.0[2] as u32 * 114) / 1000;
+                let faded = (255 - (255 - luma) / 4) as u8;
+                Rgba([faded, faded, faded, 255])
+            }
+            _ => {
+                different_pixels += 1;
+                Rgba([255, 0, 0, 255])
+            }
+        }
+    });
+    (diff, different_pixels)
+}
+
+pub fn write_html(changes: &[ImageChange], output: &Path) -> Result<PathBuf, anyhow::Error> {
+    fs::create_dir_all(output)?;
+    let output = output.canonicalize()?;
+    let src = |path: &Option<PathBuf>| -> String {
+        path.as_ref()
+            .and_then(|path| path.canonicalize().ok())
+            .map(|path| html_escape(&relative_link(&output, &path)))
+            .unwrap_or_default()
+    };
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>diff_images</title>\n<style>\n\
+         body { font-family: sans-serif; }\n\
+         table { border-collapse: collapse; }\n\
+         td, th { border: 1px solid #ccc; padding: 4px; vertical-align: top; }\n\
+         img { max-width: 400px; }\n\
+         .changed { color: #b35900; } .added { color: #2e7d32; } .removed { color: #c62828; }\n\
+         </style>\n</head>\n<body>\n<h1>diff_images</h1>\n",
+    );
+    for (status, title) in [
+        (Status::Changed, "Changed"),
+        (Status::Added, "Added"),
+        (Status::Removed, "Removed"),
+    ] {
+        let selected: Vec<&ImageChange> = changes.iter().filter(|c| c.status == status).collect();
+        writeln!(html, "<h2>{} ({})</h2>", title, selected.len())?;
+        if selected.is_empty() {
+            continue;
+        }
+        writeln!(
+            html,
+            "<table>\n<tr><th>Path</th><th>Old</th><th>New</th><th>Diff</th></tr>"
+        )?;
+        for change in selected {
+            let ratio = if change.total_pixels > 0 {
+                format!(
+                    "<br>{:.2}% pixels changed",
+                    change.different_pixels as f64 * 100.0 / change.total_pixels as f64
+                )
+            } else {
+                String::new()
+            };
+            let cell = |path: &Option<PathBuf>| -> String {
+                if path.is_some() {
+                    format!("<img src=\"{}\">", src(path))
+                } else {
+                    String::new()
+                }
+            };
+            writeln!(
+                html,
+                "<tr class=\"{}\"><td>{}{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                status.as_str(),
+                html_escape(&change.path.display().to_string()),
+                ratio,
+                cell(&change.old),
+                cell(&change.new),
+                cell(&change.diff)
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+    html.push_str("</body>\n</html>\n");
+
+    let index = output.join("index.html");
+    fs::write(&index, html)?;
+    Ok(index)
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_counts_changed_and_missing_pixels() {
+        let old = RgbaImage::from_pixel(4, 2, Rgba([10, 10, 10, 255]));
+        let mut new = RgbaImage::from_pixel(4, 3, Rgba([10, 10, 10, 255]));
+        new.put_pixel(0, 0, Rgba([12, 10, 10, 255]));
+
+        let (diff, different_pixels) = diff_image(&old, &new, 0);
+        assert_eq!((diff.width(), diff.height()), (4, 3));
+        // one changed pixel and the new row
+        assert_eq!(different_pixels, 5);
+        assert_eq!(diff.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+
+        let (_, different_pixels) = diff_image(&old, &new, 2);
+        assert_eq!(different_pixels, 4);
+    }
+}
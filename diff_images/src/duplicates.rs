@@ -0,0 +1,85 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files with the same content, the canonical one is kept
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DuplicateGroup {
+    pub canonical: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// Groups byte-identical files, or with `pixels` the images identical once decoded
+pub fn find_duplicates(
+    files_map: &BTreeSet<PathBuf>,
+    pixels: bool,
+) -> Result<Vec<DuplicateGroup>, anyhow::Error> {
+    let mut by_hash: BTreeMap<Vec<u8>, Vec<PathBuf>> = BTreeMap::new();
+    for path in files_map {
+        by_hash
+            .entry(content_hash(path, pixels)?)
+            .or_default()
+            .push(path.clone());
+    }
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .map(|mut paths| {
+            // files_map is sorted, the first path is the canonical one
+            let canonical = paths.remove(0);
+            DuplicateGroup {
+                canonical,
+                duplicates: paths,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.canonical.cmp(&b.canonical));
+    Ok(groups)
+}
+
+fn content_hash(path: &Path, pixels: bool) -> Result<Vec<u8>, anyhow::Error> {
+    let mut hasher = Sha256::new();
+    let image = if pixels { image::open(path).ok() } else { None };
+    match image {
+        Some(image) => {
+            let image = image.to_rgba8();
+            hasher.update(b"pixels");
+            hasher.update(image.width().to_le_bytes());
+            hasher.update(image.height().to_le_bytes());
+            hasher.update(image.as_raw());
+        }
+        _ => {
+            hasher.update(b"bytes");
+            hasher.update(fs::read(path)?);
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_identical_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let directory = temp.path();
+        for (name, content) in [("a.png", "same"), ("b.png", "same"), ("c.png", "other")] {
+            fs::write(directory.join(name), content).unwrap();
+        }
+        let files_map: BTreeSet<PathBuf> = ["a.png", "b.png", "c.png"]
+            .iter()
+            .map(|name| directory.join(name))
+            .collect();
+
+        assert_eq!(
+            find_duplicates(&files_map, false).unwrap(),
+            vec![DuplicateGroup {
+                canonical: directory.join("a.png"),
+                duplicates: vec![directory.join("b.png")],
+            }]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use git2::{Delta, Repository, Sort, Status, StatusOptions};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub id: String,
+    pub summary: String,
+    /// Number of commits since, 0 for HEAD
+    pub age: usize,
+}
+
+/// The state of a file in the repository containing it
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct GitState {
+    pub tracked: bool,
+    /// Changed in the index or in the working tree
+    pub modified: bool,
+    /// The commit adding the file, if among the recent ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_in: Option<Commit>,
+}
+
+/// The files added by the last `recent` commits, by absolute path
+fn recently_added(
+    repo: &Repository,
+    workdir: &Path,
+    recent: usize,
+) -> Result<HashMap<PathBuf, Commit>, anyhow::Error> {
+    let mut added = HashMap::new();
+    let mut revwalk = repo.revwalk()?;
+    // no commit yet
+    if revwalk.push_head().is_err() {
+        return Ok(added);
+    }
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    for (age, oid) in revwalk.take(recent).enumerate() {
+        let commit = repo.find_commit(oid?)?;
+        let parent = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?.tree()?),
+        };
+        let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+        for delta in diff.deltas() {
+            if delta.status() != Delta::Added {
+                continue;
+            }
+            if let Some(path) = delta.new_file().path() {
+                // the newest addition wins, the walk starts from HEAD
+                added.entry(workdir.join(path)).or_insert_with(|| Commit {
+                    id: commit.id().to_string()[..7].to_string(),
+                    summary: commit.summary().unwrap_or_default().to_string(),
+                    age,
+                });
+            }
+        }
+    }
+    Ok(added)
+}
+
+/// The git state of absolute paths, `None` outside of a repository
+pub fn git_states(
+    paths: &[PathBuf],
+    recent: usize,
+) -> Result<Option<BTreeMap<PathBuf, GitState>>, anyhow::Error> {
+    let Some(first) = paths.first() else {
+        return Ok(Some(BTreeMap::new()));
+    };
+    let Ok(repo) = Repository::discover(first.parent().unwrap_or(first)) else {
+        return Ok(None);
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(None);
+    };
+    let workdir = workdir.canonicalize()?;
+
+    let mut added = recently_added(&repo, &workdir, recent)?;
+    let index = repo.index()?;
+    let mut modified = HashMap::new();
+    for entry in repo
+        .statuses(Some(StatusOptions::new().include_untracked(false)))?
+        .iter()
+    {
+        if let Some(path) = entry.path() {
+            modified.insert(workdir.join(path), entry.status());
+        }
+    }
+    let changed = Status::INDEX_MODIFIED
+        | Status::INDEX_TYPECHANGE
+        | Status::WT_MODIFIED
+        | Status::WT_TYPECHANGE;
+
+    let mut states = BTreeMap::new();
+    for path in paths {
+        let Ok(relative) = path.strip_prefix(&workdir) else {
+            states.insert(path.clone(), GitState::default());
+            continue;
+        };
+        states.insert(
+            path.clone(),
+            GitState {
+                tracked: index.get_path(relative, 0).is_some(),
+                modified: modified
+                    .get(path)
+                    .is_some_and(|status| status.intersects(changed)),
+                added_in: added.remove(path),
+            },
+        );
+    }
+    Ok(Some(states))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn commit(repo: &Repository, files: &[&str], message: &str) {
+        let mut index = repo.index().unwrap();
+        for file in files {
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            parent.iter().collect::<Vec<_>>().as_slice(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn states() {
+        let temp = tempfile::tempdir().unwrap();
+        let directory = temp.path();
+        let repo = Repository::init(directory).unwrap();
+        for file in ["old.png", "new.png", "changed.png", "untracked.png"] {
+            fs::write(directory.join(file), file).unwrap();
+        }
+        commit(&repo, &["old.png", "changed.png"], "Old pages");
+        commit(&repo, &["new.png"], "New page");
+        fs::write(directory.join("changed.png"), "changed").unwrap();
+
+        let directory = directory.canonicalize().unwrap();
+        let paths: Vec<PathBuf> = ["old.png", "new.png", "changed.png", "untracked.png"]
+            .iter()
+            .map(|file| directory.join(file))
+            .collect();
+        let states = git_states(&paths, 1).unwrap().unwrap();
+        assert!(states[&paths[0]].tracked && states[&paths[0]].added_in.is_none());
+        assert_eq!(
+            states[&paths[1]]
+                .added_in
+                .as_ref()
+                .map(|commit| commit.summary.as_str()),
+            Some("New page")
+        );
+        assert!(states[&paths[2]].modified);
+        assert!(!states[&paths[3]].tracked);
+    }
+}
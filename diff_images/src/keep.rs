@@ -0,0 +1,127 @@
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const IGNORE_FILE: &str = ".diffimagesignore";
+
+/// Glob patterns of the assets never to delete, read from the `.diffimagesignore` files.
+/// As in `.gitignore`, a pattern is relative to the folder of its file, and a pattern without
+/// `/` matches the file name in any folder.
+#[derive(Default, Debug)]
+pub struct IgnoreList {
+    patterns: Vec<(PathBuf, Pattern)>,
+}
+
+impl IgnoreList {
+    /// Reads the ignore files of the directories and of their parents
+    pub fn load(directories: &[String]) -> Result<Self, anyhow::Error> {
+        let mut list = IgnoreList::default();
+        let mut read = Vec::new();
+        for directory in directories {
+            let directory = Path::new(directory).canonicalize()?;
+            for folder in directory.ancestors() {
+                let file = folder.join(IGNORE_FILE);
+                if !read.contains(&file) && file.is_file() {
+                    list.add(folder, &fs::read_to_string(&file)?)?;
+                    read.push(file);
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    pub fn add(&mut self, base: &Path, content: &str) -> Result<(), anyhow::Error> {
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.patterns.push((
+                base.to_path_buf(),
+                Pattern::new(line.trim_start_matches('/'))?,
+            ));
+        }
+        Ok(())
+    }
+
+    /// The pattern matching the asset, an absolute path
+    pub fn matching(&self, path: &Path) -> Option<&Pattern> {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        self.patterns.iter().find_map(|(base, pattern)| {
+            let relative = path.strip_prefix(base).ok()?;
+            let matched = if pattern.as_str().contains('/') {
+                pattern.matches_path_with(relative, options)
+            } else {
+                relative
+                    .file_name()
+                    .is_some_and(|name| pattern.matches_with(&name.to_string_lossy(), options))
+            };
+            matched.then_some(pattern)
+        })
+    }
+}
+
+/// Finds the `diff_images:keep` markers listing assets used outside of the docs, e.g.
+/// `<!-- diff_images:keep ../assets/en/logo.png -->` in Markdown or
+/// `{/* diff_images:keep ./assets/banner.png */}` in MDX
+pub struct KeepMarkers {
+    regex: Regex,
+}
+
+impl KeepMarkers {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            regex: Regex::new(
+                r"(?:<!--|\{/\*)\s*diff_images:keep\s+(?P<links>[^\n]*?)\s*(?:-->|\*/\})",
+            )?,
+        })
+    }
+
+    /// The links of every marker, relative to the document
+    pub fn extract<'a>(&self, content: &'a str) -> Vec<&'a str> {
+        self.regex
+            .captures_iter(content)
+            .filter_map(|caps| caps.name("links"))
+            .flat_map(|links| links.as_str().split_whitespace())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_patterns() {
+        let mut list = IgnoreList::default();
+        list.add(
+            Path::new("/site"),
+            "# used by docusaurus.config.js\nlogo*.png\n/docs/assets/en/social/**\n",
+        )
+        .unwrap();
+        assert!(list
+            .matching(Path::new("/site/docs/assets/logo-dark.png"))
+            .is_some());
+        assert!(list
+            .matching(Path::new("/site/docs/assets/en/social/card.png"))
+            .is_some());
+        assert!(list
+            .matching(Path::new("/site/i18n/fr/assets/en/social/card.png"))
+            .is_none());
+        assert!(list.matching(Path::new("/other/logo.png")).is_none());
+    }
+
+    #[test]
+    fn keep_markers() {
+        let markers = KeepMarkers::new().unwrap();
+        assert_eq!(
+            markers.extract(
+                "<!-- diff_images:keep ../assets/a.png ../assets/b.png -->\n{/* diff_images:keep ./assets/c.png */}\n<!-- other -->"
+            ),
+            vec!["../assets/a.png", "../assets/b.png", "./assets/c.png"]
+        );
+    }
+}
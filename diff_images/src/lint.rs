@@ -0,0 +1,193 @@
+use image::{ImageDecoder, ImageReader};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct Budget {
+    /// Maximum file size in bytes
+    #[arg(long, default_value_t = 1_000_000)]
+    pub max_bytes: u64,
+    /// Maximum width in pixels
+    #[arg(long, default_value_t = 4000)]
+    pub max_width: u32,
+    /// Maximum height in pixels
+    #[arg(long, default_value_t = 4000)]
+    pub max_height: u32,
+    /// Widest image the doc theme displays without scaling it down (content column of 900px
+    /// on high-density screens)
+    #[arg(long, default_value_t = 1800)]
+    pub theme_width: u32,
+    /// Expected resolution of the screenshots in dots per inch, not checked if not given
+    #[arg(long)]
+    pub dpi: Option<u32>,
+    /// Report the images with an alpha channel
+    #[arg(long, default_value_t = false)]
+    pub no_alpha: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    Size,
+    Dimensions,
+    ThemeWidth,
+    Dpi,
+    Alpha,
+}
+
+impl LintKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintKind::Size => "size",
+            LintKind::Dimensions => "dimensions",
+            LintKind::ThemeWidth => "theme_width",
+            LintKind::Dpi => "dpi",
+            LintKind::Alpha => "alpha",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct LintIssue {
+    pub path: PathBuf,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+pub fn lint_image(path: &Path, budget: &Budget) -> Result<Vec<LintIssue>, anyhow::Error> {
+    let mut issues = Vec::new();
+    let mut issue = |kind, message| {
+        issues.push(LintIssue {
+            path: path.to_path_buf(),
+            kind,
+            message,
+        })
+    };
+
+    let bytes = fs::read(path)?;
+    if bytes.len() as u64 > budget.max_bytes {
+        issue(
+            LintKind::Size,
+            format!("{} bytes, maximum {}", bytes.len(), budget.max_bytes),
+        );
+    }
+
+    // the other checks need a raster image
+    let Ok(decoder) = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(anyhow::Error::from)
+        .and_then(|reader| Ok(reader.into_decoder()?))
+    else {
+        return Ok(issues);
+    };
+    let (width, height) = decoder.dimensions();
+    if width > budget.max_width || height > budget.max_height {
+        issue(
+            LintKind::Dimensions,
+            format!(
+                "{}x{} pixels, maximum {}x{}",
+                width, height, budget.max_width, budget.max_height
+            ),
+        );
+    }
+    if width > budget.theme_width {
+        issue(
+            LintKind::ThemeWidth,
+            format!(
+                "{} pixels wide, the theme displays at most {}",
+                width, budget.theme_width
+            ),
+        );
+    }
+    if budget.no_alpha && decoder.color_type().has_alpha() {
+        issue(LintKind::Alpha, "alpha channel".to_string());
+    }
+    if let (Some(expected), Some((x, y))) = (budget.dpi, read_dpi(&bytes)) {
+        if x.abs_diff(expected) > 1 || y.abs_diff(expected) > 1 {
+            issue(
+                LintKind::Dpi,
+                format!("{}x{} dpi, expected {}", x, y, expected),
+            );
+        }
+    }
+    Ok(issues)
+}
+
+/// Resolution stored in a PNG `pHYs` chunk or a JPEG JFIF header
+pub fn read_dpi(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let mut offset = 8;
+        while offset + 8 <= bytes.len() {
+            let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into().ok()?) as usize;
+            let kind = &bytes[offset + 4..offset + 8];
+            let data = bytes.get(offset + 8..offset + 8 + length)?;
+            if kind == b"pHYs" && length == 9 {
+                // only pixels per meter can be converted
+                if data[8] != 1 {
+                    return None;
+                }
+                let x = u32::from_be_bytes(data[0..4].try_into().ok()?);
+                let y = u32::from_be_bytes(data[4..8].try_into().ok()?);
+                return Some((
+                    (x as f64 * 0.0254).round() as u32,
+                    (y as f64 * 0.0254).round() as u32,
+                ));
+            }
+            if kind == b"IDAT" {
+                return None;
+            }
+            offset += 12 + length;
+        }
+    } else if bytes.starts_with(&[0xFF, 0xD8]) && bytes.get(6..11) == Some(b"JFIF\0") {
+        let x = u16::from_be_bytes(bytes.get(14..16)?.try_into().ok()?) as u32;
+        let y = u16::from_be_bytes(bytes.get(16..18)?.try_into().ok()?) as u32;
+        return match bytes.get(13)? {
+            1 => Some((x, y)),
+            2 => Some((
+                (x as f64 * 2.54).round() as u32,
+                (y as f64 * 2.54).round() as u32,
+            )),
+            _ => None,
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_with_phys(pixels_per_meter: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        bytes.extend(13u32.to_be_bytes());
+        bytes.extend(b"IHDR");
+        bytes.extend([0; 13 + 4]);
+        bytes.extend(9u32.to_be_bytes());
+        bytes.extend(b"pHYs");
+        bytes.extend(pixels_per_meter.to_be_bytes());
+        bytes.extend(pixels_per_meter.to_be_bytes());
+        bytes.push(1);
+        bytes.extend([0; 4]);
+        bytes
+    }
+
+    #[test]
+    fn png_dpi() {
+        assert_eq!(read_dpi(&png_with_phys(3780)), Some((96, 96)));
+        assert_eq!(read_dpi(&png_with_phys(5669)), Some((144, 144)));
+    }
+
+    #[test]
+    fn jfif_dpi() {
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+        bytes.extend(b"JFIF\0");
+        bytes.extend([1, 1, 1, 0, 72, 0, 72, 0, 0]);
+        assert_eq!(read_dpi(&bytes), Some((72, 72)));
+    }
+
+    #[test]
+    fn unknown_dpi() {
+        assert_eq!(read_dpi(b"GIF89a"), None);
+    }
+}
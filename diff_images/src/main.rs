@@ -1,95 +1,541 @@
-use clap::Parser;
+mod case;
+mod compare;
+mod duplicates;
+mod git;
+mod keep;
+mod lint;
+mod perceptual;
+mod references;
+mod report;
+mod suggest;
+mod translations;
+mod trash;
+mod usage;
+
+use case::CaseChecker;
+use clap::{Parser, Subcommand};
 use colored::Colorize;
+use compare::Status;
 use glob::glob;
-use regex::Regex;
+use keep::{IgnoreList, KeepMarkers};
+use perceptual::HashAlgorithm;
+use rayon::prelude::*;
+use references::{
+    decode_link, encode_link, position, relative_link, replace_links, Language, Reference,
+    ReferenceExtractor,
+};
+use report::{BrokenLink, Format, LinkError, Report, UnusedAsset};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::{collections::HashSet, fs::File, io::Read};
+use std::path::{Path, PathBuf};
+use std::{fs::File, io::Read, io::Write};
+use trash::Trash;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     //If the program fix
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, global = true, default_value_t = false)]
     fix: bool,
 
     /// The paths to check
-    #[arg(short, long, num_args = 1.., value_delimiter = ' ')]
+    #[arg(short, long, global = true, num_args = 1.., value_delimiter = ' ')]
     paths: Vec<String>,
 
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, global = true, default_value_t = false)]
     verbose: bool,
+
+    /// The asset extensions to check (case-insensitive)
+    #[arg(
+        short,
+        long,
+        global = true,
+        num_args = 1..,
+        value_delimiter = ' ',
+        default_values = [
+            "png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "bmp", "ico", "pdf", "zip",
+        ]
+    )]
+    extensions: Vec<String>,
+
+    /// With --fix, move the unused assets into this folder instead of deleting them
+    #[arg(short, long, global = true)]
+    trash: Option<PathBuf>,
+
+    /// The output format, the text format is the only one printed while scanning
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Other files or folders referencing assets, e.g. `docusaurus.config.js` `sidebars.js` `src/`
+    #[arg(short, long, global = true, num_args = 1.., value_delimiter = ' ')]
+    sources: Vec<String>,
+
+    /// Withhold the deletion of the unused assets added by the last N commits, a page using them
+    /// may still be in progress (0 to disable)
+    #[arg(long, global = true, default_value_t = 20)]
+    recent: usize,
+
+    /// Check all the paths together: an asset used from any of them is kept
+    #[arg(short, long, global = true, default_value_t = false)]
+    global: bool,
 }
 
-fn find_unused_images(directory: &str, verbose: bool) -> Result<Vec<PathBuf>, anyhow::Error> {
-    println!("Directory: {}", directory);
-    let mut list_to_delete = Vec::new();
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Move back the assets listed in the manifest of a trash folder
+    Restore {
+        /// The trash folder given to --trash
+        #[arg(short, long)]
+        trash: PathBuf,
+    },
+    /// Find the assets with the same content, with --fix the links are pointed to a single copy
+    /// and the other copies are removed
+    Duplicates {
+        /// Also group the images that are identical once decoded
+        #[arg(long, default_value_t = false)]
+        pixels: bool,
+    },
+    /// Group the images that look alike, e.g. screenshots captured again with a few pixels changed
+    Similar {
+        #[arg(long, value_enum, default_value_t = HashAlgorithm::Dhash)]
+        algorithm: HashAlgorithm,
+        /// Maximum number of different bits between the 64-bit hashes of two similar images
+        #[arg(long, default_value_t = 5)]
+        threshold: u32,
+    },
+    /// Report the images over the size, dimension, resolution or alpha channel budget
+    Lint {
+        #[command(flatten)]
+        budget: lint::Budget,
+    },
+    /// Show the git state of the unused assets, which would be deleted by --fix
+    Plan,
+    /// List the pages referencing each image, and the images of each page
+    Usage,
+    /// Compare the assets of each translation with the English ones
+    Translations {
+        /// The root folder of the documentation (where the `package.json` is located)
+        #[arg(short, long)]
+        doc_folder: String,
+    },
+    /// Compare the images of two trees paired by relative path, e.g. two versions of the docs
+    Compare {
+        /// The reference tree
+        #[arg(long)]
+        old: String,
+        /// The tree compared to the reference
+        #[arg(long)]
+        new: String,
+        /// The folder receiving the diff images and index.html
+        #[arg(short, long, default_value = "diff_images_report")]
+        output: PathBuf,
+        /// Maximum difference of a channel for two pixels to be considered equal
+        #[arg(long, default_value_t = 0)]
+        tolerance: u8,
+    },
+}
+
+fn is_asset(path: &Path, extensions: &HashSet<String>) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extensions.contains(&extension.to_lowercase()))
+}
 
-    let regex: Regex = Regex::new(r#"\[.*?\]\(([^ \)]*assets\/.*?)( "(.+)")?\)"#)?;
-    let mut files_map: HashSet<PathBuf> = HashSet::new();
-    let mut images_used_set: HashSet<PathBuf> = HashSet::new();
-    let mut has_invalid_links = false;
+/// Assets and links found under one or several directories
+#[derive(Default)]
+struct Scan {
+    files_map: BTreeSet<PathBuf>,
+    /// The used images with the pages referencing them
+    images_used_set: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+    broken_links: Vec<BrokenLink>,
+    /// The images listed by a keep marker with the page of the marker
+    kept: BTreeMap<PathBuf, PathBuf>,
+}
 
-    for entry in glob(format!("{}**/assets/**/*.png", directory).as_str())?
-        .chain(glob(format!("{}**/assets/**/*.PNG", directory).as_str())?)
-    {
+impl Scan {
+    fn merge(&mut self, other: Scan) {
+        self.files_map.extend(other.files_map);
+        for (image, pages) in other.images_used_set {
+            self.images_used_set.entry(image).or_default().extend(pages);
+        }
+        self.broken_links.extend(other.broken_links);
+        for (image, page) in other.kept {
+            self.kept.entry(image).or_insert(page);
+        }
+    }
+}
+
+/// The files under the `assets` folders with one of the --extensions
+fn collect_assets(directory: &str, args: &Args) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let extensions: HashSet<String> = args
+        .extensions
+        .iter()
+        .map(|extension| extension.trim_start_matches('.').to_lowercase())
+        .collect();
+    let mut assets = Vec::new();
+    for entry in glob(format!("{}**/assets/**/*", directory).as_str())? {
         match entry {
             Ok(path) => {
-                files_map.insert(path.canonicalize()?);
-                if verbose {
-                    println!("Image found {}", path.display());
+                if is_asset(&path, &extensions) {
+                    assets.push(path);
                 }
             }
-            Err(e) => println!("{:?}", e),
+            Err(e) => eprintln!("{:?}", e),
         }
     }
-    for entry in glob(format!("{}**/*.md", directory).as_str())?
-        .chain(glob(format!("{}**/*.mdx", directory).as_str())?)
-    {
+    Ok(assets)
+}
+
+/// The pages, scripts, style sheets and JSON files of a directory, outside of `node_modules`
+fn source_files(directory: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut files = Vec::new();
+    for entry in glob(format!("{}**/*", directory).as_str())? {
         match entry {
             Ok(path) => {
-                let mut content = String::new();
-                let _ = File::open(path.as_path())?.read_to_string(&mut content);
-                let mut start = 0;
-                let temp = path.as_path().parent();
-                if let Some(temp) = temp {
-                    while let Some(caps) = regex.captures(&content[start..]) {
-                        if let Some(full_match) = caps.get(1) {
-                            let link = caps.get(1).map(|m| m.as_str()).unwrap_or("");
-                            if !link.starts_with("http") {
-                                let final_path = temp.join(std::path::Path::new(link));
-                                match fs::canonicalize(final_path) {
-                                    Ok(final_path) => {
-                                        if verbose {
-                                            println!(
-                                                "Link found {}",
-                                                &final_path.as_path().display()
-                                            );
-                                        }
-                                        images_used_set.insert(final_path);
-                                    }
-                                    Err(_) => {
-                                        has_invalid_links = true;
-                                        println!(
-                                            "Error with image path {} {}",
-                                            link.red(),
-                                            path.as_path().display()
-                                        )
-                                    }
-                                }
-                            }
-
-                            start += full_match.end();
-                        }
-                    }
+                if Language::of(&path).is_some()
+                    && path.is_file()
+                    && !path.components().any(|component| {
+                        component == std::path::Component::Normal("node_modules".as_ref())
+                    })
+                {
+                    files.push(path);
                 }
             }
-            Err(e) => println!("{:?}", e),
+            Err(e) => eprintln!("{:?}", e),
+        }
+    }
+    Ok(files)
+}
+
+/// The files of the directory and of the --sources
+fn scanned_files(directory: &str, args: &Args) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut files = source_files(directory)?;
+    for source in &args.sources {
+        for entry in glob(source)? {
+            let path = entry?;
+            if path.is_dir() {
+                files.extend(source_files(&format!("{}/", path.display()))?);
+            } else if Language::of(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// The root of the Docusaurus site, the target of `@site/` links
+fn site_root(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|folder| {
+        folder.join("docusaurus.config.js").is_file()
+            || folder.join("docusaurus.config.ts").is_file()
+    })
+}
+
+/// The path a link of the source points to, the strings of scripts and JSON files are not
+/// percent-encoded
+fn link_path(source: &Path, link: &str) -> String {
+    match Language::of(source) {
+        Some(Language::Script | Language::Json) => link.to_string(),
+        _ => decode_link(link),
+    }
+}
+
+/// The link to a path in the source, the inverse of `link_path`
+fn path_link(source: &Path, path: &str) -> String {
+    match Language::of(source) {
+        Some(Language::Script | Language::Json) => path.to_string(),
+        _ => encode_link(path),
+    }
+}
+
+/// The folder a decoded link of a page is relative to, and the link relative to this folder:
+/// `@site/` links are relative to the site root
+fn link_base<'a>(directory: &'a Path, page: &'a Path, link: &'a str) -> (&'a Path, &'a str) {
+    match link.strip_prefix("@site/") {
+        Some(relative) => (site_root(page).unwrap_or(directory), relative),
+        None => (directory, link),
+    }
+}
+
+/// The asset a link relative to `base` points to, and the link spelled as on disk if it differs
+/// in case only
+fn resolve_link(
+    base: &Path,
+    relative: &str,
+    case_checker: &mut CaseChecker,
+) -> (Option<PathBuf>, Option<String>) {
+    let corrected = case_checker.correct(base, relative);
+    let resolved = base.join(relative).canonicalize().ok().or_else(|| {
+        corrected
+            .as_ref()
+            .and_then(|corrected| base.join(corrected).canonicalize().ok())
+    });
+    (resolved, corrected)
+}
+
+/// The images a page links to, scanned independently of the other pages
+struct PageScan {
+    page: PathBuf,
+    used: Vec<PathBuf>,
+    broken_links: Vec<BrokenLink>,
+    kept: Vec<PathBuf>,
+}
+
+fn scan_page(
+    path: &Path,
+    extractor: &ReferenceExtractor,
+    markers: &KeepMarkers,
+    case_checker: &mut CaseChecker,
+) -> Result<PageScan, anyhow::Error> {
+    let mut content = String::new();
+    let _ = File::open(path)?.read_to_string(&mut content);
+    let mut page = PageScan {
+        page: path.canonicalize()?,
+        used: Vec::new(),
+        broken_links: Vec::new(),
+        kept: Vec::new(),
+    };
+    let Some(temp) = path.parent() else {
+        return Ok(page);
+    };
+    for link in markers.extract(&content) {
+        page.kept.extend(temp.join(link).canonicalize().ok());
+    }
+    let language = Language::of(path).unwrap_or(Language::Markdown);
+    for reference in extractor.extract_as(&content, language) {
+        let decoded = link_path(path, &reference.link);
+        let link = decoded.as_str();
+        if link.starts_with("http") {
+            continue;
+        }
+        let (base, relative) = link_base(temp, &page.page, link);
+        let (resolved, corrected) = resolve_link(base, relative, case_checker);
+        // a string of a script or a JSON file may be anything, e.g. a package path
+        let explicit = language == Language::Markdown
+            || link.starts_with("./")
+            || link.starts_with("../")
+            || link.starts_with("@site/");
+        if (resolved.is_none() && explicit) || corrected.is_some() {
+            let (line, column) = position(&content, reference.offset);
+            page.broken_links.push(BrokenLink {
+                source: path.to_path_buf(),
+                line,
+                column,
+                link: reference.link.clone(),
+                error: if corrected.is_some() {
+                    LinkError::CaseMismatch
+                } else {
+                    LinkError::NotFound
+                },
+                offset: reference.offset,
+                suggestion: corrected.map(|corrected| {
+                    let corrected =
+                        format!("{}{}", &link[..link.len() - relative.len()], corrected);
+                    path_link(path, &corrected)
+                }),
+            });
         }
+        page.used.extend(resolved);
     }
-    for path in images_used_set {
-        if files_map.contains(&path) {
-            files_map.remove(&path);
+    Ok(page)
+}
+
+/// Reads, extracts and resolves the pages in parallel, then merges them in path order so the
+/// output does not depend on the scheduling
+fn scan_directory(directory: &str, args: &Args) -> Result<Scan, anyhow::Error> {
+    let text = args.format == Format::Text;
+    if text {
+        println!("Directory: {}", directory);
+    }
+
+    let extractor = ReferenceExtractor::new()?;
+    let markers = KeepMarkers::new()?;
+    let mut scan = Scan::default();
+    let verbose = args.verbose && text;
+
+    let assets = collect_assets(directory, args)?;
+    let canonical = assets
+        .par_iter()
+        .map(|path| path.canonicalize())
+        .collect::<Result<Vec<_>, _>>()?;
+    for (path, canonical) in assets.iter().zip(canonical) {
+        scan.files_map.insert(canonical);
+        if verbose {
+            println!("Image found {}", path.display());
+        }
+    }
+
+    let pages = scanned_files(directory, args)?
+        .par_iter()
+        .map_init(CaseChecker::default, |case_checker, path| {
+            scan_page(path, &extractor, &markers, case_checker)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for page in pages {
+        for image in page.used {
+            if verbose {
+                println!("Link found {}", image.display());
+            }
+            scan.images_used_set
+                .entry(image)
+                .or_default()
+                .insert(page.page.clone());
+        }
+        scan.broken_links.extend(page.broken_links);
+        for image in page.kept {
+            scan.kept.entry(image).or_insert_with(|| page.page.clone());
+        }
+    }
+
+    Ok(scan)
+}
+
+/// Prints the broken links with the asset they were most likely meant for.
+/// With --fix, the links are rewritten to these assets.
+fn report_broken_links(
+    scan: &mut Scan,
+    args: &Args,
+    report: &mut Report,
+) -> Result<(), anyhow::Error> {
+    let mut replacements: BTreeMap<PathBuf, Vec<(Reference, String)>> = BTreeMap::new();
+    let mut still_broken = Vec::new();
+    for mut broken in std::mem::take(&mut scan.broken_links) {
+        // the suggestion of a case mismatch is the link spelled as on disk
+        let case_mismatch = broken.error == LinkError::CaseMismatch;
+        let mut unambiguous = case_mismatch;
+        let mut candidate = None;
+        let directory = broken
+            .source
+            .parent()
+            .and_then(|parent| parent.canonicalize().ok());
+        if let (false, Some(directory)) = (case_mismatch, directory) {
+            let intended =
+                suggest::normalize(&directory.join(link_path(&broken.source, &broken.link)));
+            candidate = suggest::best_candidate(&intended, &scan.files_map).cloned();
+            unambiguous = candidate.as_ref().is_some_and(|candidate| {
+                suggest::is_unambiguous(&intended, candidate, &scan.files_map)
+            });
+            broken.suggestion = candidate
+                .as_ref()
+                .map(|candidate| path_link(&broken.source, &relative_link(&directory, candidate)));
+        }
+
+        // a close name may be another image, it is only suggested
+        let fixed = args.fix && unambiguous && broken.suggestion.is_some();
+        if args.format == Format::Text {
+            let message = if case_mismatch {
+                "Case mismatch in image path"
+            } else {
+                "Error with image path"
+            };
+            match &broken.suggestion {
+                Some(suggestion) if fixed => println!(
+                    "{}: {} {}, replaced by {}",
+                    broken.location(),
+                    message,
+                    broken.link.red(),
+                    suggestion.green()
+                ),
+                Some(suggestion) => println!(
+                    "{}: {} {}, did you mean {}?",
+                    broken.location(),
+                    message,
+                    broken.link.red(),
+                    suggestion.green()
+                ),
+                None => println!("{}: {} {}", broken.location(), message, broken.link.red()),
+            }
+        }
+        match &broken.suggestion {
+            Some(suggestion) if fixed => {
+                if let Some(candidate) = candidate {
+                    scan.images_used_set
+                        .entry(candidate)
+                        .or_default()
+                        .insert(broken.source.canonicalize()?);
+                }
+                replacements
+                    .entry(broken.source.clone())
+                    .or_default()
+                    .push((
+                        Reference {
+                            link: broken.link.clone(),
+                            offset: broken.offset,
+                        },
+                        suggestion.clone(),
+                    ));
+            }
+            _ if !case_mismatch => still_broken.push(broken.clone()),
+            _ => {}
+        }
+        report.broken_links.push(broken);
+    }
+    scan.broken_links = still_broken;
+
+    for (path, replacements) in replacements {
+        let mut content = fs::read_to_string(&path)?;
+        replace_links(&mut content, replacements);
+        fs::write(&path, content)?;
+    }
+    Ok(())
+}
+
+/// Whether a broken link could be meant for this asset: same file name or a close one
+fn may_target(link: &str, image: &Path) -> bool {
+    let link_name = link
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(link)
+        .to_lowercase();
+    let Some(image_name) = image
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+    else {
+        return false;
+    };
+    if link_name.is_empty() {
+        return false;
+    }
+    if link_name == image_name {
+        return true;
+    }
+    let stem = |name: &str| {
+        name.rsplit_once('.')
+            .map_or(name, |(stem, _)| stem)
+            .to_string()
+    };
+    strsim::normalized_levenshtein(&stem(&link_name), &stem(&image_name)) >= 0.8
+}
+
+/// Why an asset is kept although the pages do not use it: a keep marker or an ignore pattern
+fn kept_by(image: &Path, kept: &BTreeMap<PathBuf, PathBuf>, ignore: &IgnoreList) -> Option<String> {
+    if let Some(page) = kept.get(image) {
+        return Some(format!("kept by {}", page.display()));
+    }
+    ignore
+        .matching(image)
+        .map(|pattern| format!("kept by {} pattern {}", keep::IGNORE_FILE, pattern))
+}
+
+fn find_unused_images(
+    scan: Scan,
+    args: &Args,
+    ignore: &IgnoreList,
+    report: &mut Report,
+) -> Vec<PathBuf> {
+    let verbose = args.verbose && args.format == Format::Text;
+    let mut list_to_delete = Vec::new();
+    let mut files_map = scan.files_map;
+    for path in scan.images_used_set.keys() {
+        if files_map.contains(path) {
+            files_map.remove(path);
         }
     }
     if !files_map.is_empty() {
@@ -97,40 +543,771 @@ fn find_unused_images(directory: &str, verbose: bool) -> Result<Vec<PathBuf>, an
             println!("{}", "To DELETE:".red());
         }
         for image in files_map {
+            if let Some(reason) = kept_by(&image, &scan.kept, ignore) {
+                if verbose {
+                    println!("{} {}", image.display(), reason);
+                }
+                continue;
+            }
             if verbose {
                 println!("{} image not used", image.as_path().display());
             }
-            if !has_invalid_links {
-                list_to_delete.push(image.clone());
+            let withheld_by = scan
+                .broken_links
+                .iter()
+                .find(|broken| may_target(&link_path(&broken.source, &broken.link), &image))
+                .cloned();
+            match &withheld_by {
+                Some(broken) => {
+                    if args.format == Format::Text {
+                        println!(
+                            "{} deletion withheld, it may be the target of {} at {}",
+                            image.as_path().display().to_string().yellow(),
+                            broken.link,
+                            broken.location()
+                        );
+                    }
+                }
+                None => list_to_delete.push(image.clone()),
             }
+            report.unused.push(UnusedAsset {
+                path: image.clone(),
+                withheld_by,
+                added_in: None,
+            });
         }
     }
 
-    Ok(list_to_delete)
+    list_to_delete
 }
 
-fn main() -> Result<(), anyhow::Error> {
-    let args = Args::parse();
-    let directories: Vec<String> = args.paths;
-    let mut counter = 0;
-    for directory in &directories {
+/// Expands the globs of --paths into directories ending with '/'
+fn expand_directories(paths: &[String]) -> Result<Vec<String>, anyhow::Error> {
+    let mut directories = Vec::new();
+    for directory in paths {
         for entry in glob(directory)? {
             if let Some(mut path) = entry?
                 .to_str()
                 .map(|str| str.replace(std::path::MAIN_SEPARATOR_STR, "/"))
             {
                 path.push('/');
-                let vec = find_unused_images(&path, args.verbose)?;
-                counter += vec.len();
-                if args.fix {
-                    for path in vec {
-                        std::fs::remove_file(path)?;
+                directories.push(path);
+            }
+        }
+    }
+    Ok(directories)
+}
+
+/// Deletes the files, or moves them into the --trash folder
+fn remove_files(
+    args: &Args,
+    files: impl IntoIterator<Item = (PathBuf, String)>,
+) -> Result<(), anyhow::Error> {
+    let mut trash = args.trash.as_deref().map(Trash::open).transpose()?;
+    for (path, reason) in files {
+        match trash.as_mut() {
+            Some(trash) => trash.move_file(&path, &reason)?,
+            None => std::fs::remove_file(path)?,
+        }
+    }
+    Ok(())
+}
+
+/// The unused assets, with the git state of the ones that can be deleted. The assets recently
+/// added to git are withheld.
+fn unused_images(
+    args: &Args,
+    report: &mut Report,
+) -> Result<BTreeMap<PathBuf, Option<git::GitState>>, anyhow::Error> {
+    let directories = expand_directories(&args.paths)?;
+    let ignore = IgnoreList::load(&directories)?;
+    let mut list_to_delete = Vec::new();
+    if args.global {
+        let mut scan = Scan::default();
+        for directory in &directories {
+            scan.merge(scan_directory(directory, args)?);
+        }
+        report_broken_links(&mut scan, args, report)?;
+        list_to_delete = find_unused_images(scan, args, &ignore, report);
+    } else {
+        for directory in &directories {
+            let mut scan = scan_directory(directory, args)?;
+            report_broken_links(&mut scan, args, report)?;
+            list_to_delete.extend(find_unused_images(scan, args, &ignore, report));
+        }
+    }
+
+    let mut states = git::git_states(&list_to_delete, args.recent)?.unwrap_or_default();
+    for unused in &mut report.unused {
+        unused.added_in = states
+            .get(&unused.path)
+            .and_then(|state| state.added_in.clone());
+    }
+    Ok(list_to_delete
+        .into_iter()
+        .map(|path| {
+            let state = states.remove(&path);
+            (path, state)
+        })
+        .collect())
+}
+
+fn print_withheld(path: &Path, commit: &git::Commit) {
+    println!(
+        "{} deletion withheld, added {} commit(s) ago in {} {}",
+        path.display().to_string().yellow(),
+        commit.age,
+        commit.id,
+        commit.summary
+    );
+}
+
+fn check_unused(args: &Args) -> Result<(), anyhow::Error> {
+    let mut report = Report::default();
+    let mut list_to_delete = Vec::new();
+    for (path, state) in unused_images(args, &mut report)? {
+        match state.and_then(|state| state.added_in) {
+            Some(commit) => {
+                if args.format == Format::Text {
+                    print_withheld(&path, &commit);
+                }
+            }
+            None => list_to_delete.push(path),
+        }
+    }
+
+    if args.fix {
+        remove_files(
+            args,
+            list_to_delete
+                .iter()
+                .map(|path| (path.clone(), "not referenced".to_string())),
+        )?;
+    }
+    if args.format == Format::Text {
+        println!("Number images not used: {}", list_to_delete.len());
+    } else {
+        report.write(args.format, &mut std::io::stdout().lock())?;
+    }
+
+    Ok(())
+}
+
+/// The fate of an unused asset with --fix
+#[derive(serde::Serialize)]
+struct PlannedDeletion {
+    path: PathBuf,
+    action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git: Option<git::GitState>,
+}
+
+fn check_plan(args: &Args) -> Result<(), anyhow::Error> {
+    if args.fix {
+        anyhow::bail!("The plan command does not modify anything, remove --fix");
+    }
+    let plan: Vec<PlannedDeletion> = unused_images(args, &mut Report::default())?
+        .into_iter()
+        .map(|(path, git)| PlannedDeletion {
+            action: match git.as_ref().and_then(|git| git.added_in.as_ref()) {
+                Some(_) => "withheld",
+                None => "delete",
+            },
+            path,
+            git,
+        })
+        .collect();
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            for planned in &plan {
+                let state = match &planned.git {
+                    None => "not in git".to_string(),
+                    Some(git) => match &git.added_in {
+                        Some(commit) => format!(
+                            "added {} commit(s) ago in {} {}",
+                            commit.age, commit.id, commit.summary
+                        ),
+                        None if git.modified => "modified".to_string(),
+                        None if git.tracked => "tracked".to_string(),
+                        None => "untracked".to_string(),
+                    },
+                };
+                let line = format!(
+                    "{:<8} {} ({})",
+                    planned.action,
+                    planned.path.display(),
+                    state
+                );
+                if planned.action == "withheld" {
+                    println!("{}", line.yellow());
+                } else {
+                    println!("{}", line);
+                }
+            }
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&plan)?)?,
+        Format::Csv => {
+            writeln!(out, "path,action,tracked,modified,added_in")?;
+            for planned in &plan {
+                let git = planned.git.clone().unwrap_or_default();
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    report::csv_field(&planned.path.display().to_string()),
+                    planned.action,
+                    git.tracked,
+                    git.modified,
+                    git.added_in.map(|commit| commit.id).unwrap_or_default()
+                )?;
+            }
+        }
+        Format::Junit => anyhow::bail!("The junit format is not supported by this command"),
+    }
+
+    Ok(())
+}
+
+/// Points the links of the pages to a duplicate to its canonical file, returns the number of
+/// links changed
+fn rewrite_duplicate_links(
+    pages: &BTreeSet<PathBuf>,
+    groups: &[duplicates::DuplicateGroup],
+    args: &Args,
+) -> Result<usize, anyhow::Error> {
+    let extractor = ReferenceExtractor::new()?;
+    let mut case_checker = CaseChecker::default();
+    let canonicals: HashMap<&PathBuf, &PathBuf> = groups
+        .iter()
+        .flat_map(|group| {
+            group
+                .duplicates
+                .iter()
+                .map(move |duplicate| (duplicate, &group.canonical))
+        })
+        .collect();
+
+    let mut counter = 0;
+    for path in pages {
+        let Some(directory) = path.parent() else {
+            continue;
+        };
+        let mut content = fs::read_to_string(path)?;
+        let language = Language::of(path).unwrap_or(Language::Markdown);
+        let mut replacements = Vec::new();
+        for reference in extractor.extract_as(&content, language) {
+            let decoded = link_path(path, &reference.link);
+            let (base, relative) = link_base(directory, path, &decoded);
+            let (resolved, _) = resolve_link(base, relative, &mut case_checker);
+            let Some(canonical) = resolved.and_then(|target| canonicals.get(&target).copied())
+            else {
+                continue;
+            };
+            let new_link = if decoded.starts_with("@site/") {
+                format!(
+                    "@site/{}",
+                    relative_link(base, canonical).trim_start_matches("./")
+                )
+            } else {
+                relative_link(directory, canonical)
+            };
+            let new_link = path_link(path, &new_link);
+            if args.format == Format::Text {
+                println!("Link: {} {} {}", path.display(), reference.link, new_link);
+            }
+            replacements.push((reference, new_link));
+        }
+        if !replacements.is_empty() {
+            counter += replacements.len();
+            replace_links(&mut content, replacements);
+            fs::write(path, content)?;
+        }
+    }
+    Ok(counter)
+}
+
+/// Removes the duplicates once their links are rewritten, except the ones still referenced, kept
+/// or recently added
+fn remove_duplicates(
+    args: &Args,
+    directories: &[String],
+    scan: &Scan,
+    groups: &[duplicates::DuplicateGroup],
+) -> Result<(), anyhow::Error> {
+    let text = args.format == Format::Text;
+    let duplicates: BTreeSet<&PathBuf> =
+        groups.iter().flat_map(|group| &group.duplicates).collect();
+    let pages: BTreeSet<PathBuf> = scan
+        .images_used_set
+        .iter()
+        .filter(|(image, _)| duplicates.contains(image))
+        .flat_map(|(_, pages)| pages.iter().cloned())
+        .collect();
+    let counter = rewrite_duplicate_links(&pages, groups, args)?;
+    if text {
+        println!("Number links updated: {}", counter);
+    }
+
+    // scanned again, a link the rewriting missed still uses its duplicate
+    let extractor = ReferenceExtractor::new()?;
+    let markers = KeepMarkers::new()?;
+    let mut case_checker = CaseChecker::default();
+    let mut still_used = BTreeSet::new();
+    for page in &pages {
+        still_used.extend(scan_page(page, &extractor, &markers, &mut case_checker)?.used);
+    }
+
+    let ignore = IgnoreList::load(directories)?;
+    let mut removable = Vec::new();
+    for group in groups {
+        for duplicate in &group.duplicates {
+            let kept = kept_by(duplicate, &scan.kept, &ignore).or_else(|| {
+                still_used
+                    .contains(duplicate)
+                    .then(|| "still referenced".to_string())
+            });
+            match kept {
+                Some(reason) => {
+                    if text {
+                        println!(
+                            "{} not removed, {}",
+                            duplicate.display().to_string().yellow(),
+                            reason
+                        );
                     }
                 }
+                None => removable.push((
+                    duplicate.clone(),
+                    format!("duplicate of {}", group.canonical.display()),
+                )),
+            }
+        }
+    }
+
+    let paths: Vec<PathBuf> = removable.iter().map(|(path, _)| path.clone()).collect();
+    let states = git::git_states(&paths, args.recent)?.unwrap_or_default();
+    removable.retain(|(path, _)| {
+        match states.get(path).and_then(|state| state.added_in.as_ref()) {
+            Some(commit) => {
+                if text {
+                    print_withheld(path, commit);
+                }
+                false
+            }
+            None => true,
+        }
+    });
+    remove_files(args, removable)
+}
+
+fn check_duplicates(args: &Args, pixels: bool) -> Result<(), anyhow::Error> {
+    let directories = expand_directories(&args.paths)?;
+    let mut scan = Scan::default();
+    for directory in &directories {
+        scan.merge(scan_directory(directory, args)?);
+    }
+
+    let groups = duplicates::find_duplicates(&scan.files_map, pixels)?;
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            for group in &groups {
+                println!("{}", group.canonical.display().to_string().green());
+                for duplicate in &group.duplicates {
+                    println!("    {} same content", duplicate.display());
+                }
+            }
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&groups)?)?,
+        Format::Csv => {
+            writeln!(out, "canonical,duplicate")?;
+            for group in &groups {
+                for duplicate in &group.duplicates {
+                    writeln!(
+                        out,
+                        "{},{}",
+                        report::csv_field(&group.canonical.display().to_string()),
+                        report::csv_field(&duplicate.display().to_string())
+                    )?;
+                }
+            }
+        }
+        Format::Junit => anyhow::bail!("The junit format is not supported by this command"),
+    }
+
+    if args.fix {
+        remove_duplicates(args, &directories, &scan, &groups)?;
+    }
+    if args.format == Format::Text {
+        println!(
+            "Number duplicates: {}",
+            groups
+                .iter()
+                .map(|group| group.duplicates.len())
+                .sum::<usize>()
+        );
+    }
+
+    Ok(())
+}
+
+fn check_similar(
+    args: &Args,
+    algorithm: HashAlgorithm,
+    threshold: u32,
+) -> Result<(), anyhow::Error> {
+    let mut hashes = Vec::new();
+    for directory in expand_directories(&args.paths)? {
+        for path in collect_assets(&directory, args)? {
+            match image::open(&path) {
+                Ok(image) => hashes.push((
+                    path.canonicalize()?,
+                    perceptual::image_hash(&image, algorithm),
+                )),
+                Err(e) => {
+                    if args.verbose {
+                        eprintln!("{} {}", path.display(), e);
+                    }
+                }
+            }
+        }
+    }
+    hashes.sort();
+    hashes.dedup_by(|a, b| a.0 == b.0);
+
+    let clusters = perceptual::cluster(&hashes, threshold);
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            for (i, cluster) in clusters.iter().enumerate() {
+                println!("{}", format!("Cluster {}", i + 1).green());
+                for image in cluster {
+                    println!("    {} distance {}", image.path.display(), image.distance);
+                }
+            }
+            println!("Number similar clusters: {}", clusters.len());
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&clusters)?)?,
+        Format::Csv => {
+            writeln!(out, "cluster,path,distance")?;
+            for (i, cluster) in clusters.iter().enumerate() {
+                for image in cluster {
+                    writeln!(
+                        out,
+                        "{},{},{}",
+                        i + 1,
+                        report::csv_field(&image.path.display().to_string()),
+                        image.distance
+                    )?;
+                }
+            }
+        }
+        Format::Junit => anyhow::bail!("The junit format is not supported by this command"),
+    }
+
+    Ok(())
+}
+
+/// The assets of a tree indexed by their path relative to the tree
+fn assets_by_relative_path(
+    root: &str,
+    args: &Args,
+) -> Result<BTreeMap<PathBuf, PathBuf>, anyhow::Error> {
+    let mut directory = root.replace(std::path::MAIN_SEPARATOR_STR, "/");
+    if !directory.ends_with('/') {
+        directory.push('/');
+    }
+    let mut assets = BTreeMap::new();
+    for path in collect_assets(&directory, args)? {
+        if let Ok(relative) = path.strip_prefix(&directory) {
+            assets.insert(relative.to_path_buf(), path.clone());
+        }
+    }
+    Ok(assets)
+}
+
+fn check_compare(
+    args: &Args,
+    old: &str,
+    new: &str,
+    output: &Path,
+    tolerance: u8,
+) -> Result<(), anyhow::Error> {
+    let changes = compare::compare_trees(
+        assets_by_relative_path(old, args)?,
+        assets_by_relative_path(new, args)?,
+        output,
+        tolerance,
+    )?;
+    let index = compare::write_html(&changes, output)?;
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            for change in &changes {
+                let status = match change.status {
+                    Status::Changed => change.status.as_str().yellow(),
+                    Status::Added => change.status.as_str().green(),
+                    Status::Removed => change.status.as_str().red(),
+                };
+                println!("{} {}", status, change.path.display());
+            }
+            println!("Report: {}", index.display());
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&changes)?)?,
+        Format::Csv => {
+            writeln!(out, "status,path,different_pixels,total_pixels")?;
+            for change in &changes {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    change.status.as_str(),
+                    report::csv_field(&change.path.display().to_string()),
+                    change.different_pixels,
+                    change.total_pixels
+                )?;
+            }
+        }
+        Format::Junit => anyhow::bail!("The junit format is not supported by this command"),
+    }
+
+    Ok(())
+}
+
+fn check_usage(args: &Args) -> Result<(), anyhow::Error> {
+    let mut scan = Scan::default();
+    for directory in expand_directories(&args.paths)? {
+        scan.merge(scan_directory(&directory, args)?);
+    }
+    let index = usage::usage_index(&scan.files_map, &scan.images_used_set);
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            println!("{}", "Images".green());
+            for image in &index.images {
+                println!("{}", image.path.display());
+                if image.pages.is_empty() {
+                    println!("    {}", "not used".yellow());
+                }
+                for page in &image.pages {
+                    println!("    {} {}", page.language, page.path.display());
+                }
+            }
+            println!("{}", "Pages".green());
+            for page in &index.pages {
+                println!("{} {}", page.language, page.path.display());
+                for image in &page.images {
+                    println!("    {}", image.display());
+                }
+            }
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&index)?)?,
+        Format::Csv => {
+            writeln!(out, "image,page,language")?;
+            for image in &index.images {
+                let path = report::csv_field(&image.path.display().to_string());
+                if image.pages.is_empty() {
+                    writeln!(out, "{},,", path)?;
+                }
+                for page in &image.pages {
+                    writeln!(
+                        out,
+                        "{},{},{}",
+                        path,
+                        report::csv_field(&page.path.display().to_string()),
+                        page.language
+                    )?;
+                }
             }
         }
+        Format::Junit => anyhow::bail!("The junit format is not supported by this command"),
     }
-    println!("Number images not used: {}", counter);
 
     Ok(())
 }
+
+fn check_translations(args: &Args, doc_folder: &str) -> Result<(), anyhow::Error> {
+    let doc_folder = doc_folder.trim_end_matches(['/', '\\']);
+    let mut english_trees = vec![("current".to_string(), format!("{}/docs/", doc_folder))];
+    for entry in glob(format!("{}/versioned_docs/version-*/", doc_folder).as_str())? {
+        let path = entry?;
+        if let Some(version) = path.file_name() {
+            english_trees.push((
+                version.to_string_lossy().to_string(),
+                format!("{}/", path.display()),
+            ));
+        }
+    }
+
+    let mut assets = Vec::new();
+    for entry in glob(format!("{}/i18n/*/docusaurus-plugin-content-docs/", doc_folder).as_str())? {
+        let language_folder = entry?;
+        let language = usage::language(&language_folder);
+        for (version, english_tree) in &english_trees {
+            let translated_tree = format!("{}/{}/", language_folder.display(), version);
+            if !Path::new(&translated_tree).is_dir() {
+                continue;
+            }
+            let scan = scan_directory(&translated_tree, args)?;
+            assets.extend(translations::compare_translation(
+                &language,
+                version,
+                &assets_by_relative_path(english_tree, args)?,
+                &assets_by_relative_path(&translated_tree, args)?,
+                &scan.images_used_set,
+            )?);
+        }
+    }
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            for asset in &assets {
+                let issue = match asset.issue {
+                    translations::Issue::Stale => asset.issue.as_str().red(),
+                    translations::Issue::OnlyInTranslation => asset.issue.as_str().yellow(),
+                    translations::Issue::Differs => asset.issue.as_str().normal(),
+                };
+                println!(
+                    "{} {} {} {}",
+                    issue,
+                    asset.language,
+                    asset.version,
+                    asset.translation.display()
+                );
+            }
+            println!("Number translated assets to check: {}", assets.len());
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&assets)?)?,
+        Format::Csv => {
+            writeln!(out, "issue,language,version,translation,english")?;
+            for asset in &assets {
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    asset.issue.as_str(),
+                    asset.language,
+                    asset.version,
+                    report::csv_field(&asset.translation.display().to_string()),
+                    asset
+                        .english
+                        .as_ref()
+                        .map(|english| report::csv_field(&english.display().to_string()))
+                        .unwrap_or_default()
+                )?;
+            }
+        }
+        Format::Junit => anyhow::bail!("The junit format is not supported by this command"),
+    }
+
+    Ok(())
+}
+
+fn check_lint(args: &Args, budget: &lint::Budget) -> Result<(), anyhow::Error> {
+    let mut issues = Vec::new();
+    for directory in expand_directories(&args.paths)? {
+        for path in collect_assets(&directory, args)? {
+            issues.extend(lint::lint_image(&path, budget)?);
+        }
+    }
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            for issue in &issues {
+                println!(
+                    "{}: {} {}",
+                    issue.path.display(),
+                    issue.kind.as_str().yellow(),
+                    issue.message
+                );
+            }
+            println!("Number lint issues: {}", issues.len());
+        }
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&issues)?)?,
+        Format::Csv => {
+            writeln!(out, "path,kind,message")?;
+            for issue in &issues {
+                writeln!(
+                    out,
+                    "{},{},{}",
+                    report::csv_field(&issue.path.display().to_string()),
+                    issue.kind.as_str(),
+                    report::csv_field(&issue.message)
+                )?;
+            }
+        }
+        Format::Junit => {
+            writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(
+                out,
+                r#"<testsuite name="lint" tests="{0}" failures="{0}">"#,
+                issues.len()
+            )?;
+            for issue in &issues {
+                let path = report::xml_escape(&issue.path.display().to_string());
+                writeln!(
+                    out,
+                    r#"  <testcase classname="{}" name="{}" file="{}">"#,
+                    issue.kind.as_str(),
+                    path,
+                    path
+                )?;
+                writeln!(
+                    out,
+                    r#"    <failure message="{}">{}</failure>"#,
+                    report::xml_escape(&issue.message),
+                    path
+                )?;
+                writeln!(out, "  </testcase>")?;
+            }
+            writeln!(out, "</testsuite>")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Args::parse();
+    match &args.command {
+        Some(Command::Restore { trash }) => {
+            let counter = trash::restore(trash)?;
+            println!("Number files restored: {}", counter);
+            Ok(())
+        }
+        Some(Command::Duplicates { pixels }) => check_duplicates(&args, *pixels),
+        Some(Command::Similar {
+            algorithm,
+            threshold,
+        }) => check_similar(&args, *algorithm, *threshold),
+        Some(Command::Lint { budget }) => check_lint(&args, budget),
+        Some(Command::Plan) => check_plan(&args),
+        Some(Command::Usage) => check_usage(&args),
+        Some(Command::Translations { doc_folder }) => check_translations(&args, doc_folder),
+        Some(Command::Compare {
+            old,
+            new,
+            output,
+            tolerance,
+        }) => check_compare(&args, old, new, output, *tolerance),
+        None => check_unused(&args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_link_targets() {
+        let image = Path::new("/docs/assets/en/API/dataClass.png");
+        assert!(may_target("../assets/en/dataClass.png", image));
+        assert!(may_target("../assets/en/API/DataClass.PNG", image));
+        assert!(may_target("../assets/en/API/dataClasses.png", image));
+        assert!(!may_target("../assets/en/API/entity.png", image));
+        assert!(!may_target("../assets/en/API/", image));
+    }
+}
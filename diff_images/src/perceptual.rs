@@ -0,0 +1,169 @@
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum HashAlgorithm {
+    /// Average hash: pixels brighter than the mean
+    Ahash,
+    /// Difference hash: brightness gradient between neighbours
+    #[default]
+    Dhash,
+    /// Perceptual hash: low frequencies of the discrete cosine transform
+    Phash,
+}
+
+/// 64-bit perceptual hash of an image
+pub fn image_hash(image: &DynamicImage, algorithm: HashAlgorithm) -> u64 {
+    match algorithm {
+        HashAlgorithm::Ahash => {
+            let pixels = grayscale(image, 8, 8);
+            let mean = pixels.iter().sum::<f64>() / pixels.len() as f64;
+            bits(pixels.iter().map(|&p| p > mean))
+        }
+        HashAlgorithm::Dhash => {
+            let pixels = grayscale(image, 9, 8);
+            bits((0..8).flat_map(|y| {
+                let row = &pixels[y * 9..(y + 1) * 9];
+                (0..8).map(move |x| row[x] < row[x + 1])
+            }))
+        }
+        HashAlgorithm::Phash => {
+            const SIZE: usize = 32;
+            let pixels = grayscale(image, SIZE as u32, SIZE as u32);
+            let mut coefficients = Vec::with_capacity(64);
+            for v in 0..8 {
+                for u in 0..8 {
+                    coefficients.push(dct(&pixels, SIZE, u, v));
+                }
+            }
+            // the first coefficient is the mean brightness, it does not describe the picture
+            let mut sorted = coefficients[1..].to_vec();
+            sorted.sort_by(|a, b| a.total_cmp(b));
+            let median = sorted[sorted.len() / 2];
+            bits(coefficients.iter().map(|&c| c > median))
+        }
+    }
+}
+
+fn grayscale(image: &DynamicImage, width: u32, height: u32) -> Vec<f64> {
+    image
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_luma8()
+        .pixels()
+        .map(|p| p.0[0] as f64)
+        .collect()
+}
+
+fn dct(pixels: &[f64], size: usize, u: usize, v: usize) -> f64 {
+    let n = size as f64;
+    let mut sum = 0.0;
+    for y in 0..size {
+        for x in 0..size {
+            sum += pixels[y * size + x]
+                * (std::f64::consts::PI * (2.0 * x as f64 + 1.0) * u as f64 / (2.0 * n)).cos()
+                * (std::f64::consts::PI * (2.0 * y as f64 + 1.0) * v as f64 / (2.0 * n)).cos();
+        }
+    }
+    sum
+}
+
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.fold(0, |hash, bit| (hash << 1) | bit as u64)
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SimilarImage {
+    pub path: PathBuf,
+    /// Number of different bits with the first image of the cluster
+    pub distance: u32,
+}
+
+/// Groups the images whose hashes differ by at most `threshold` bits, directly or through other images
+pub fn cluster(hashes: &[(PathBuf, u64)], threshold: u32) -> Vec<Vec<SimilarImage>> {
+    let mut parents: Vec<usize> = (0..hashes.len()).collect();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            if (hashes[i].1 ^ hashes[j].1).count_ones() <= threshold {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..hashes.len() {
+        let r = root(&mut parents, i);
+        clusters.entry(r).or_default().push(i);
+    }
+    clusters
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let first = hashes[members[0]].1;
+            members
+                .into_iter()
+                .map(|i| SimilarImage {
+                    path: hashes[i].0.clone(),
+                    distance: (hashes[i].1 ^ first).count_ones(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn gradient(noise: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 48, |x, y| {
+            let value = (x * 3 + y) as u8;
+            Rgb([
+                value.saturating_add(noise * ((x + y) % 2) as u8),
+                value,
+                value,
+            ])
+        }))
+    }
+
+    #[test]
+    fn similar_images_have_close_hashes() {
+        for algorithm in [
+            HashAlgorithm::Ahash,
+            HashAlgorithm::Dhash,
+            HashAlgorithm::Phash,
+        ] {
+            let a = image_hash(&gradient(0), algorithm);
+            let b = image_hash(&gradient(4), algorithm);
+            let c = image_hash(&gradient(0).fliph(), algorithm);
+            assert!((a ^ b).count_ones() <= 5, "{:?}", algorithm);
+            assert!((a ^ c).count_ones() > 10, "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn cluster_is_transitive() {
+        let hashes = vec![
+            (PathBuf::from("a"), 0b0000),
+            (PathBuf::from("b"), 0b0011),
+            (PathBuf::from("c"), 0b1111),
+            (PathBuf::from("d"), u64::MAX),
+        ];
+        let clusters = cluster(&hashes, 2);
+        assert_eq!(clusters.len(), 1);
+        let distances: Vec<_> = clusters[0].iter().map(|image| image.distance).collect();
+        assert_eq!(distances, vec![0, 2, 4]);
+    }
+}
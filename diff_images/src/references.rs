@@ -0,0 +1,444 @@
+use regex::Regex;
+use std::ops::Range;
+use std::path::{Component, Path};
+
+/// A link to an asset found in a Markdown/MDX document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub link: String,
+    /// Byte offset of the link in the document
+    pub offset: usize,
+}
+
+/// The syntax of a file that may reference assets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Markdown,
+    /// JavaScript and TypeScript, e.g. `sidebars.js`, `docusaurus.config.js`, `src/**/*.tsx`
+    Script,
+    Css,
+    Json,
+}
+
+impl Language {
+    pub fn of(path: &Path) -> Option<Language> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "md" | "mdx" => Some(Language::Markdown),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => Some(Language::Script),
+            "css" | "scss" => Some(Language::Css),
+            "json" => Some(Language::Json),
+            _ => None,
+        }
+    }
+}
+
+pub struct ReferenceExtractor {
+    regexes: Vec<(Language, Regex)>,
+}
+
+impl ReferenceExtractor {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        let patterns = [
+            // ![alt](../assets/x.png "title"), [text](<../assets/my file.pdf>), matched from `](`
+            // so the outer link of a linked image is found too
+            (
+                Language::Markdown,
+                r#"\]\([ \t]*\n?[ \t]*(?P<destination>)"#,
+            ),
+            // [id]: ../assets/x.png "title"
+            (
+                Language::Markdown,
+                r#"(?m)^ {0,3}\[[^\]\n]+\]:[ \t]*\n?[ \t]*(?P<destination>)"#,
+            ),
+            // <img src="../assets/x.png" />, <a href="...">, <video poster="...">
+            (
+                Language::Markdown,
+                r#"<[a-zA-Z][^>]*?\s(?:src|href|poster)\s*=\s*["'](?P<link>[^"']+)["']"#,
+            ),
+            // import pic from './assets/x.png';
+            (
+                Language::Markdown,
+                r#"(?m)^\s*import\s+(?:[\w*{}\s,]+\s+from\s+)?["'](?P<link>[^"']+)["']"#,
+            ),
+            // require('./assets/x.png')
+            (
+                Language::Markdown,
+                r#"require\(\s*["'](?P<link>[^"']+)["']\s*\)"#,
+            ),
+            // any string literal: imports, require(), config values, JSX attributes. The quotes
+            // are matched left to right so `"Don't"` does not open a '...' string.
+            (
+                Language::Script,
+                r#"(?P<string>"(?:[^"\\\n]|\\.)*"|'(?:[^'\\\n]|\\.)*'|`(?:[^`\\]|\\.)*`)"#,
+            ),
+            // background: url("../assets/x.png") and @import "x.css"
+            (Language::Css, r#"url\(\s*["']?(?P<link>[^"')\s]+)"#),
+            (Language::Css, r#"@import\s+["'](?P<link>[^"']+)["']"#),
+            (Language::Json, r#""(?P<link>[^"\n]+)""#),
+        ];
+        let regexes = patterns
+            .iter()
+            .map(|(language, pattern)| Ok((*language, Regex::new(pattern)?)))
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        Ok(Self { regexes })
+    }
+
+    /// Returns every link to an `assets/` folder, in document order. Links built at runtime,
+    /// e.g. `${name}.png`, and the samples of the Markdown code blocks are skipped.
+    pub fn extract_as(&self, content: &str, language: Language) -> Vec<Reference> {
+        let code = match language {
+            Language::Markdown => fenced_blocks(content),
+            _ => Vec::new(),
+        };
+        let mut references = Vec::new();
+        for (_, regex) in self.regexes.iter().filter(|(of, _)| *of == language) {
+            for caps in regex.captures_iter(content) {
+                let range = match (caps.name("link"), caps.name("destination")) {
+                    (Some(link), _) => Some(link.range()),
+                    (None, Some(start)) => destination(content, start.start()),
+                    // a string literal without its quotes
+                    (None, None) => caps
+                        .name("string")
+                        .map(|string| string.start() + 1..string.end() - 1),
+                };
+                let Some(range) = range else {
+                    continue;
+                };
+                if code.iter().any(|block| block.contains(&range.start)) {
+                    continue;
+                }
+                let link = &content[range.clone()];
+                if link.to_lowercase().contains("assets/") && !link.contains("${") {
+                    references.push(Reference {
+                        link: link.to_string(),
+                        offset: range.start,
+                    });
+                }
+            }
+        }
+        references.sort_by_key(|reference| reference.offset);
+        references.dedup_by_key(|reference| reference.offset);
+        references
+    }
+}
+
+/// The byte ranges of the fenced code blocks of a Markdown document, fences included. A block
+/// left open runs to the end of the document.
+fn fenced_blocks(content: &str) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            match open {
+                Some((fence, start)) if fence == marker => {
+                    blocks.push(start..offset + line.len());
+                    open = None;
+                }
+                None => open = Some((marker, offset)),
+                _ => {}
+            }
+        }
+        offset += line.len();
+    }
+    if let Some((_, start)) = open {
+        blocks.push(start..content.len());
+    }
+    blocks
+}
+
+/// The range of the CommonMark link destination starting at `start`, without its angle brackets.
+/// A destination without brackets ends at a space or at an unbalanced `)`.
+fn destination(content: &str, start: usize) -> Option<Range<usize>> {
+    let rest = &content[start..];
+    let mut escaped = false;
+    if let Some(inner) = rest.strip_prefix('<') {
+        for (i, c) in inner.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '\n' | '<' => return None,
+                '>' => return Some(start + 1..start + 1 + i),
+                _ => {}
+            }
+        }
+        return None;
+    }
+    let mut depth = 0;
+    let mut end = rest.len();
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                end = i;
+                break;
+            }
+            ')' => depth -= 1,
+            _ if c.is_whitespace() || c.is_control() => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    (end > 0).then_some(start..start + end)
+}
+
+/// The path of a Markdown link: backslash escapes removed and percent-encoding decoded
+pub fn decode_link(link: &str) -> String {
+    let mut unescaped = String::with_capacity(link.len());
+    let mut chars = link.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && next.is_ascii_punctuation() => {}
+            _ => unescaped.push(c),
+        }
+    }
+    urlencoding::decode(&unescaped)
+        .map(|decoded| decoded.into_owned())
+        .unwrap_or(unescaped)
+}
+
+/// A path written as a Markdown link, the spaces percent-encoded
+pub fn encode_link(path: &str) -> String {
+    path.replace(' ', "%20")
+}
+
+/// 1-based line and column (in characters) of a byte offset
+pub fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Relative link from a directory to a file, starting with `./` or `../` so MDX imports stay relative
+pub fn relative_link(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    if parts.is_empty() {
+        parts.push(".".to_string());
+    }
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+/// Replaces the links at the given offsets, the replacements may be in any order
+pub fn replace_links(content: &mut String, mut replacements: Vec<(Reference, String)>) {
+    replacements.sort_by_key(|(reference, _)| reference.offset);
+    for (reference, new_link) in replacements.into_iter().rev() {
+        content.replace_range(
+            reference.offset..reference.offset + reference.link.len(),
+            &new_link,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(content: &str) -> Vec<String> {
+        ReferenceExtractor::new()
+            .unwrap()
+            .extract_as(content, Language::Markdown)
+            .into_iter()
+            .map(|reference| reference.link)
+            .collect()
+    }
+
+    #[test]
+    fn inline_links() {
+        assert_eq!(
+            links(r#"![](../assets/en/a.png) and [pdf](../assets/b.pdf "title")"#),
+            vec!["../assets/en/a.png", "../assets/b.pdf"]
+        );
+        assert_eq!(
+            links("[![thumb](./assets/thumb.png)](./assets/full.png)"),
+            vec!["./assets/thumb.png", "./assets/full.png"]
+        );
+        assert!(links("[page](../API/CollectionClass.md)").is_empty());
+        assert_eq!(links("![](../Assets/en/A.png)"), vec!["../Assets/en/A.png"]);
+    }
+
+    #[test]
+    fn link_destinations() {
+        assert_eq!(
+            links("![](<../assets/en/My Image.png>) ![](../assets/en/My%20Image.png 'title')"),
+            vec!["../assets/en/My Image.png", "../assets/en/My%20Image.png"]
+        );
+        assert_eq!(
+            links("![](../assets/x(1).png) [![](../assets/a.png \"t\")](../API/a.md)"),
+            vec!["../assets/x(1).png", "../assets/a.png"]
+        );
+        assert!(links("![](<../assets/a\n.png>)").is_empty());
+    }
+
+    #[test]
+    fn decode_links() {
+        assert_eq!(
+            decode_link("../assets/My%20Image.png"),
+            "../assets/My Image.png"
+        );
+        assert_eq!(
+            decode_link(r"../assets/a\_b\(1\).png"),
+            "../assets/a_b(1).png"
+        );
+        assert_eq!(
+            encode_link("../assets/My Image.png"),
+            "../assets/My%20Image.png"
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            links("```md\n![](../assets/sample.png)\n```\n![](../assets/a.png)\n~~~\n![](../assets/b.png)"),
+            vec!["../assets/a.png"]
+        );
+    }
+
+    #[test]
+    fn reference_definitions() {
+        assert_eq!(
+            links("![shot][id]\n\n[id]: ../assets/en/shot.png \"title\"\n"),
+            vec!["../assets/en/shot.png"]
+        );
+    }
+
+    #[test]
+    fn html_tags() {
+        assert_eq!(
+            links(r#"<img src="../assets/en/a.png" alt="a" /> <a href='../assets/b.zip'>b</a>"#),
+            vec!["../assets/en/a.png", "../assets/b.zip"]
+        );
+        assert_eq!(
+            links(r#"<img alt="a" width="300" src="../assets/en/a.png"/>"#),
+            vec!["../assets/en/a.png"]
+        );
+    }
+
+    #[test]
+    fn mdx_imports_and_require() {
+        assert_eq!(
+            links("import pic from './assets/x.png';\n\n<img src={require('./assets/y.png').default} />"),
+            vec!["./assets/x.png", "./assets/y.png"]
+        );
+        assert!(links("import Tabs from '@theme/Tabs';").is_empty());
+    }
+
+    fn links_as(content: &str, language: Language) -> Vec<String> {
+        ReferenceExtractor::new()
+            .unwrap()
+            .extract_as(content, language)
+            .into_iter()
+            .map(|reference| reference.link)
+            .collect()
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(
+            links_as(
+                "import a from '@site/docs/assets/en/a.png';\nconst b = require(\"./assets/b.svg\");\n<img src={`./assets/${name}.png`} />\nimage: 'docs/assets/social.png',\nt(\"Don't\", './../docs/assets/en/c.png', 'it\\'s', \"./assets/d.png\");",
+                Language::Script
+            ),
+            vec![
+                "@site/docs/assets/en/a.png",
+                "./assets/b.svg",
+                "docs/assets/social.png",
+                "./../docs/assets/en/c.png",
+                "./assets/d.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn css_and_json() {
+        assert_eq!(
+            links_as(
+                ".hero { background: url(\"../assets/hero.png\") } .b { background: url(../assets/b.png) }",
+                Language::Css
+            ),
+            vec!["../assets/hero.png", "../assets/b.png"]
+        );
+        assert_eq!(
+            links_as(
+                r#"{"image": "./assets/card.png", "label": "API"}"#,
+                Language::Json
+            ),
+            vec!["./assets/card.png"]
+        );
+    }
+
+    #[test]
+    fn languages() {
+        assert_eq!(
+            Language::of(Path::new("sidebars.js")),
+            Some(Language::Script)
+        );
+        assert_eq!(
+            Language::of(Path::new("a/Page.MDX")),
+            Some(Language::Markdown)
+        );
+        assert_eq!(Language::of(Path::new("a.png")), None);
+    }
+
+    #[test]
+    fn offsets() {
+        let content = "text\n![](../assets/a.png)";
+        let references = ReferenceExtractor::new()
+            .unwrap()
+            .extract_as(content, Language::Markdown);
+        assert_eq!(references[0].offset, content.find("../assets").unwrap());
+        assert_eq!(position(content, references[0].offset), (2, 5));
+    }
+
+    #[test]
+    fn relative_links() {
+        assert_eq!(
+            relative_link(Path::new("/docs/API"), Path::new("/docs/assets/en/a.png")),
+            "../assets/en/a.png"
+        );
+        assert_eq!(
+            relative_link(Path::new("/docs"), Path::new("/docs/assets/a.png")),
+            "./assets/a.png"
+        );
+    }
+
+    #[test]
+    fn replace_links_keeps_offsets() {
+        let mut content = "![](../assets/a.png) ![](../assets/b.png)".to_string();
+        let references = ReferenceExtractor::new()
+            .unwrap()
+            .extract_as(&content, Language::Markdown);
+        let replacements = references
+            .into_iter()
+            .map(|reference| (reference, "../assets/common.png".to_string()))
+            .collect();
+        replace_links(&mut content, replacements);
+        assert_eq!(
+            content,
+            "![](../assets/common.png) ![](../assets/common.png)"
+        );
+    }
+
+    #[test]
+    fn position_counts_characters() {
+        let content = "é\nà ![](../assets/a.png)";
+        assert_eq!(position(content, content.find("../").unwrap()), (2, 7));
+        assert_eq!(position(content, 0), (1, 1));
+    }
+}
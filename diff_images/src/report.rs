@@ -0,0 +1,288 @@
+use crate::git::Commit;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Junit,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkError {
+    /// The target does not exist
+    NotFound,
+    /// The target exists but its name differs in case, which breaks on case-sensitive file systems
+    CaseMismatch,
+}
+
+impl LinkError {
+    /// The serde name, so a CSV record has the same kind as its JSON error
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkError::NotFound => "not_found",
+            LinkError::CaseMismatch => "case_mismatch",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BrokenLink {
+    /// The Markdown file containing the link
+    pub source: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub link: String,
+    pub error: LinkError,
+    /// Byte offset of the link in the source
+    #[serde(skip)]
+    pub offset: usize,
+    /// A link to the asset the broken link was most likely meant for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UnusedAsset {
+    pub path: PathBuf,
+    /// A broken link that may target this asset, its deletion is withheld
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withheld_by: Option<BrokenLink>,
+    /// The recent commit adding this asset, its deletion is withheld
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_in: Option<Commit>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Report {
+    pub broken_links: Vec<BrokenLink>,
+    pub unused: Vec<UnusedAsset>,
+}
+
+impl BrokenLink {
+    /// `file.md:line:column`, as in a compiler diagnostic
+    pub fn location(&self) -> String {
+        format!("{}:{}:{}", self.source.display(), self.line, self.column)
+    }
+}
+
+impl Report {
+    /// Writes the report for the machine-readable formats, the text format is printed while scanning
+    pub fn write(&self, format: Format, out: &mut impl Write) -> Result<(), anyhow::Error> {
+        match format {
+            Format::Text => {}
+            Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(self)?)?,
+            Format::Csv => self.write_csv(out)?,
+            Format::Junit => self.write_junit(out)?,
+        }
+        Ok(())
+    }
+
+    fn write_csv(&self, out: &mut impl Write) -> Result<(), anyhow::Error> {
+        writeln!(out, "kind,path,line,column,link,suggestion")?;
+        for broken in &self.broken_links {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                broken.error.as_str(),
+                csv_field(&broken.source.display().to_string()),
+                broken.line,
+                broken.column,
+                csv_field(&broken.link),
+                csv_field(broken.suggestion.as_deref().unwrap_or_default())
+            )?;
+        }
+        for unused in &self.unused {
+            match (&unused.withheld_by, &unused.added_in) {
+                (Some(broken), _) => writeln!(
+                    out,
+                    "withheld,{},,,{},",
+                    csv_field(&unused.path.display().to_string()),
+                    csv_field(&broken.link)
+                )?,
+                (None, Some(_)) => writeln!(
+                    out,
+                    "recent,{},,,,",
+                    csv_field(&unused.path.display().to_string())
+                )?,
+                (None, None) => writeln!(
+                    out,
+                    "unused,{},,,,",
+                    csv_field(&unused.path.display().to_string())
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_junit(&self, out: &mut impl Write) -> Result<(), anyhow::Error> {
+        let tests = self.broken_links.len() + self.unused.len();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuites name="diff_images" tests="{0}" failures="{0}">"#,
+            tests
+        )?;
+        writeln!(
+            out,
+            r#"  <testsuite name="broken_links" tests="{0}" failures="{0}">"#,
+            self.broken_links.len()
+        )?;
+        for broken in &self.broken_links {
+            let location = broken.location();
+            writeln!(
+                out,
+                r#"    <testcase classname="{}" name="{}" file="{}" line="{}">"#,
+                xml_escape(&broken.source.display().to_string()),
+                xml_escape(&broken.link),
+                xml_escape(&broken.source.display().to_string()),
+                broken.line
+            )?;
+            writeln!(
+                out,
+                r#"      <failure message="{} {}">{}</failure>"#,
+                match broken.error {
+                    LinkError::NotFound => "Broken image link",
+                    LinkError::CaseMismatch => "Case mismatch in image link",
+                },
+                xml_escape(&broken.link),
+                xml_escape(&location)
+            )?;
+            writeln!(out, "    </testcase>")?;
+        }
+        writeln!(out, "  </testsuite>")?;
+        writeln!(
+            out,
+            r#"  <testsuite name="unused" tests="{0}" failures="{0}">"#,
+            self.unused.len()
+        )?;
+        for unused in &self.unused {
+            let path = xml_escape(&unused.path.display().to_string());
+            writeln!(
+                out,
+                r#"    <testcase classname="unused" name="{0}" file="{0}">"#,
+                path
+            )?;
+            let message = match (&unused.withheld_by, &unused.added_in) {
+                (Some(broken), _) => format!(
+                    "Image not used, deletion withheld because of {} at {}",
+                    broken.link,
+                    broken.location()
+                ),
+                (None, Some(commit)) => format!(
+                    "Image not used, deletion withheld because it was added in {} {}",
+                    commit.id, commit.summary
+                ),
+                (None, None) => "Image not used".to_string(),
+            };
+            writeln!(
+                out,
+                r#"      <failure message="{}">{}</failure>"#,
+                xml_escape(&message),
+                path
+            )?;
+            writeln!(out, "    </testcase>")?;
+        }
+        writeln!(out, "  </testsuite>")?;
+        writeln!(out, "</testsuites>")?;
+        Ok(())
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        Report {
+            broken_links: vec![BrokenLink {
+                source: PathBuf::from("docs/API/a.md"),
+                line: 12,
+                column: 5,
+                link: "../assets/en/a, b.png".to_string(),
+                error: LinkError::NotFound,
+                offset: 0,
+                suggestion: Some("../assets/en/a_b.png".to_string()),
+            }],
+            unused: vec![
+                UnusedAsset {
+                    path: PathBuf::from("docs/assets/en/<c>.png"),
+                    withheld_by: None,
+                    added_in: None,
+                },
+                UnusedAsset {
+                    path: PathBuf::from("docs/assets/en/b.png"),
+                    withheld_by: Some(BrokenLink {
+                        source: PathBuf::from("docs/API/a.md"),
+                        line: 3,
+                        column: 1,
+                        link: "../assets/en/B.png".to_string(),
+                        error: LinkError::NotFound,
+                        offset: 0,
+                        suggestion: None,
+                    }),
+                    added_in: None,
+                },
+            ],
+        }
+    }
+
+    fn output(format: Format) -> String {
+        let mut out = Vec::new();
+        report().write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            output(Format::Csv),
+            "kind,path,line,column,link,suggestion\nnot_found,docs/API/a.md,12,5,\"../assets/en/a, b.png\",../assets/en/a_b.png\nunused,docs/assets/en/<c>.png,,,,\nwithheld,docs/assets/en/b.png,,,../assets/en/B.png,\n"
+        );
+    }
+
+    #[test]
+    fn junit_is_escaped() {
+        let junit = output(Format::Junit);
+        assert!(junit.contains(r#"<testsuites name="diff_images" tests="3" failures="3">"#));
+        assert!(junit.contains("docs/API/a.md:12:5"));
+        assert!(junit.contains("&lt;c&gt;.png"));
+    }
+
+    #[test]
+    fn json() {
+        let json: serde_json::Value = serde_json::from_str(&output(Format::Json)).unwrap();
+        assert_eq!(json["broken_links"][0]["line"], 12);
+        assert_eq!(json["broken_links"][0]["error"], "not_found");
+        assert_eq!(
+            json["broken_links"][0]["suggestion"],
+            "../assets/en/a_b.png"
+        );
+        assert_eq!(json["unused"][0]["path"], "docs/assets/en/<c>.png");
+        assert!(json["unused"][0].get("withheld_by").is_none());
+        assert_eq!(json["unused"][1]["withheld_by"]["line"], 3);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves `.` and `..` without touching the file system, the path may not exist
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The lowercase file name without its extension, `step1` for `Step1.PNG`
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// The asset a broken link was most likely meant for: a close file name, then the closest path
+pub fn best_candidate<'a>(
+    intended: &Path,
+    files_map: &'a BTreeSet<PathBuf>,
+) -> Option<&'a PathBuf> {
+    let intended_stem = stem(intended);
+    if intended_stem.is_empty() {
+        return None;
+    }
+    let intended_path = intended.to_string_lossy().to_lowercase();
+    files_map
+        .iter()
+        .filter_map(|candidate| {
+            let name_similarity = strsim::normalized_levenshtein(&intended_stem, &stem(candidate));
+            if name_similarity < 0.8 {
+                return None;
+            }
+            let path_similarity = strsim::normalized_levenshtein(
+                &intended_path,
+                &candidate.to_string_lossy().to_lowercase(),
+            );
+            Some((name_similarity * 0.7 + path_similarity * 0.3, candidate))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Whether a broken link can be pointed to the candidate without asking: the same path but for
+/// the case, or the only asset with the same file name
+pub fn is_unambiguous(intended: &Path, candidate: &Path, files_map: &BTreeSet<PathBuf>) -> bool {
+    if intended.to_string_lossy().to_lowercase() == candidate.to_string_lossy().to_lowercase() {
+        return true;
+    }
+    let name = intended.file_name();
+    name.is_some()
+        && candidate.file_name() == name
+        && files_map
+            .iter()
+            .filter(|path| path.file_name() == name)
+            .count()
+            == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files_map() -> BTreeSet<PathBuf> {
+        [
+            "/docs/assets/en/API/dataClass.png",
+            "/docs/assets/en/ORDA/dataClass.png",
+            "/docs/assets/en/ORDA/entity.png",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect()
+    }
+
+    #[test]
+    fn normalize_parent_dirs() {
+        assert_eq!(
+            normalize(Path::new("/docs/API/../assets/./en/a.png")),
+            PathBuf::from("/docs/assets/en/a.png")
+        );
+    }
+
+    #[test]
+    fn moved_image() {
+        let files_map = files_map();
+        assert_eq!(
+            best_candidate(
+                Path::new("/docs/assets/en/ORDA/sub/dataClass.png"),
+                &files_map
+            ),
+            Some(&PathBuf::from("/docs/assets/en/ORDA/dataClass.png"))
+        );
+    }
+
+    #[test]
+    fn case_only_rename() {
+        let files_map = files_map();
+        assert_eq!(
+            best_candidate(Path::new("/docs/Assets/en/API/DataClass.PNG"), &files_map),
+            Some(&PathBuf::from("/docs/assets/en/API/dataClass.png"))
+        );
+    }
+
+    #[test]
+    fn close_names() {
+        let files_map: BTreeSet<PathBuf> = ["/docs/assets/en/c.png", "/docs/assets/en/step2.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(
+            best_candidate(Path::new("/docs/assets/en/b.png"), &files_map),
+            None
+        );
+        let step1 = Path::new("/docs/assets/en/step1.png");
+        let candidate = best_candidate(step1, &files_map).unwrap();
+        assert_eq!(candidate, &PathBuf::from("/docs/assets/en/step2.png"));
+        assert!(!is_unambiguous(step1, candidate, &files_map));
+    }
+
+    #[test]
+    fn unambiguous_candidates() {
+        let files_map = files_map();
+        let moved = Path::new("/docs/assets/en/ORDA/sub/entity.png");
+        assert!(is_unambiguous(
+            moved,
+            &PathBuf::from("/docs/assets/en/ORDA/entity.png"),
+            &files_map
+        ));
+        // two images with this name
+        let moved = Path::new("/docs/assets/en/ORDA/sub/dataClass.png");
+        assert!(!is_unambiguous(
+            moved,
+            &PathBuf::from("/docs/assets/en/ORDA/dataClass.png"),
+            &files_map
+        ));
+        assert!(is_unambiguous(
+            Path::new("/docs/Assets/en/API/DataClass.PNG"),
+            &PathBuf::from("/docs/assets/en/API/dataClass.png"),
+            &files_map
+        ));
+    }
+
+    #[test]
+    fn no_candidate() {
+        assert_eq!(
+            best_candidate(Path::new("/docs/assets/en/API/session.png"), &files_map()),
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Issue {
+    /// The asset does not exist in the English tree
+    OnlyInTranslation,
+    /// The translated copy differs from the English one, e.g. a localized screenshot
+    Differs,
+    /// A translated page shows an English image whose English copy has since been replaced
+    Stale,
+}
+
+impl Issue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Issue::OnlyInTranslation => "only_in_translation",
+            Issue::Differs => "differs",
+            Issue::Stale => "stale",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TranslatedAsset {
+    pub language: String,
+    /// `current` or the `version-*` folder
+    pub version: String,
+    /// Path relative to the docs tree
+    pub path: PathBuf,
+    pub translation: PathBuf,
+    pub english: Option<PathBuf>,
+    pub issue: Issue,
+}
+
+/// Whether the asset is in an English folder, e.g. `assets/en/API/x.png`
+fn is_english(relative: &Path) -> bool {
+    relative
+        .components()
+        .any(|component| component == Component::Normal("en".as_ref()))
+}
+
+/// Compares the assets of a translated tree with the English tree, both indexed by relative path
+pub fn compare_translation(
+    language: &str,
+    version: &str,
+    english: &BTreeMap<PathBuf, PathBuf>,
+    translation: &BTreeMap<PathBuf, PathBuf>,
+    images_used_set: &BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+) -> Result<Vec<TranslatedAsset>, anyhow::Error> {
+    let mut assets = Vec::new();
+    for (relative, translated) in translation {
+        let issue = match english.get(relative) {
+            None => Some(Issue::OnlyInTranslation),
+            Some(english) if fs::read(english)? != fs::read(translated)? => {
+                let used = images_used_set.contains_key(&translated.canonicalize()?);
+                if used && is_english(relative) {
+                    Some(Issue::Stale)
+                } else {
+                    Some(Issue::Differs)
+                }
+            }
+            Some(_) => None,
+        };
+        if let Some(issue) = issue {
+            assets.push(TranslatedAsset {
+                language: language.to_string(),
+                version: version.to_string(),
+                path: relative.clone(),
+                translation: translated.clone(),
+                english: english.get(relative).cloned(),
+                issue,
+            });
+        }
+    }
+    Ok(assets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues() {
+        let temp = tempfile::tempdir().unwrap();
+        let directory = temp.path();
+        let file = |name: &str, content: &str| {
+            let path = directory.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let english = BTreeMap::from([
+            (PathBuf::from("assets/en/same.png"), file("en_same", "a")),
+            (PathBuf::from("assets/en/new.png"), file("en_new", "new")),
+            (PathBuf::from("assets/en/local.png"), file("en_local", "en")),
+        ]);
+        let translation = BTreeMap::from([
+            (PathBuf::from("assets/en/same.png"), file("fr_same", "a")),
+            (PathBuf::from("assets/en/new.png"), file("fr_new", "old")),
+            (PathBuf::from("assets/en/local.png"), file("fr_local", "fr")),
+            (PathBuf::from("assets/fr/only.png"), file("fr_only", "fr")),
+        ]);
+        let images_used_set = BTreeMap::from([(
+            directory.join("fr_new").canonicalize().unwrap(),
+            BTreeSet::from([PathBuf::from("page.md")]),
+        )]);
+
+        let issues: Vec<_> =
+            compare_translation("fr", "current", &english, &translation, &images_used_set)
+                .unwrap()
+                .into_iter()
+                .map(|asset| (asset.path, asset.issue))
+                .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (PathBuf::from("assets/en/local.png"), Issue::Differs),
+                (PathBuf::from("assets/en/new.png"), Issue::Stale),
+                (
+                    PathBuf::from("assets/fr/only.png"),
+                    Issue::OnlyInTranslation
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    pub entries: Vec<TrashEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashEntry {
+    pub original: PathBuf,
+    pub trashed: PathBuf,
+    pub reason: String,
+    /// Seconds since the Unix epoch
+    pub moved_at: u64,
+}
+
+/// Quarantine folder: files are moved there instead of being deleted
+pub struct Trash {
+    root: PathBuf,
+    manifest: Manifest,
+}
+
+impl Trash {
+    pub fn open(root: &Path) -> Result<Self, anyhow::Error> {
+        fs::create_dir_all(root)?;
+        Ok(Self {
+            root: root.canonicalize()?,
+            manifest: read_manifest(root)?,
+        })
+    }
+
+    /// Moves a file and writes the manifest at once, so the files moved before a failure can
+    /// still be restored
+    pub fn move_file(&mut self, path: &Path, reason: &str) -> Result<(), anyhow::Error> {
+        let original = path.canonicalize()?;
+        let mut trashed = self.root.join(relative_to_cwd(&original));
+        let mut counter = 1;
+        while trashed.exists() {
+            let mut file_name = original.file_name().unwrap_or_default().to_os_string();
+            file_name.push(format!("~{}", counter));
+            trashed.set_file_name(file_name);
+            counter += 1;
+        }
+        move_file(&original, &trashed)?;
+        self.manifest.entries.push(TrashEntry {
+            original,
+            trashed,
+            reason: reason.to_string(),
+            moved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        });
+        write_manifest(&self.root, &self.manifest)
+    }
+}
+
+/// Moves back every file of the manifest, the most recent first
+pub fn restore(root: &Path) -> Result<usize, anyhow::Error> {
+    let manifest = read_manifest(root)?;
+    let mut remaining = Vec::new();
+    let mut counter = 0;
+    for entry in manifest.entries.into_iter().rev() {
+        if entry.original.exists() {
+            println!(
+                "{} {} already exists",
+                "Not restored".yellow(),
+                entry.original.display()
+            );
+            remaining.push(entry);
+        } else if !entry.trashed.exists() {
+            println!(
+                "{} {} is missing from the trash",
+                "Not restored".yellow(),
+                entry.trashed.display()
+            );
+            remaining.push(entry);
+        } else {
+            move_file(&entry.trashed, &entry.original)?;
+            println!("Restored {}", entry.original.display());
+            counter += 1;
+        }
+    }
+    remaining.reverse();
+    write_manifest(root, &Manifest { entries: remaining })?;
+    Ok(counter)
+}
+
+fn read_manifest(root: &Path) -> Result<Manifest, anyhow::Error> {
+    let path = root.join(MANIFEST_NAME);
+    if !path.exists() {
+        return Ok(Manifest::default());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_manifest(root: &Path, manifest: &Manifest) -> Result<(), anyhow::Error> {
+    fs::write(
+        root.join(MANIFEST_NAME),
+        serde_json::to_string_pretty(manifest)?,
+    )?;
+    Ok(())
+}
+
+/// Keeps the layout of the docs inside the trash
+fn relative_to_cwd(path: &Path) -> PathBuf {
+    if let Ok(cwd) = std::env::current_dir().and_then(|cwd| cwd.canonicalize()) {
+        if let Ok(relative) = path.strip_prefix(cwd) {
+            return relative.to_path_buf();
+        }
+    }
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), anyhow::Error> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_err() {
+        // rename fails across file systems
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+/// The language of a docs file: the folder after `i18n`, English otherwise
+pub fn language(path: &Path) -> String {
+    let mut components = path.components();
+    while let Some(component) = components.next() {
+        if component == Component::Normal("i18n".as_ref()) {
+            if let Some(language) = components.next() {
+                return language.as_os_str().to_string_lossy().to_string();
+            }
+        }
+    }
+    "en".to_string()
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Page {
+    pub path: PathBuf,
+    pub language: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ImageUsage {
+    pub path: PathBuf,
+    pub pages: Vec<Page>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PageImages {
+    pub path: PathBuf,
+    pub language: String,
+    pub images: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct UsageIndex {
+    pub images: Vec<ImageUsage>,
+    pub pages: Vec<PageImages>,
+}
+
+/// Indexes the pages of every asset, and the assets of every page
+pub fn usage_index(
+    files_map: &BTreeSet<PathBuf>,
+    images_used_set: &BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+) -> UsageIndex {
+    let mut pages: BTreeMap<&PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for (image, sources) in images_used_set {
+        for page in sources {
+            pages.entry(page).or_default().push(image.clone());
+        }
+    }
+    UsageIndex {
+        images: files_map
+            .iter()
+            .map(|image| ImageUsage {
+                path: image.clone(),
+                pages: images_used_set
+                    .get(image)
+                    .into_iter()
+                    .flatten()
+                    .map(|page| Page {
+                        path: page.clone(),
+                        language: language(page),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        pages: pages
+            .into_iter()
+            .map(|(page, images)| PageImages {
+                path: page.clone(),
+                language: language(page),
+                images,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages() {
+        assert_eq!(language(Path::new("/site/docs/API/a.md")), "en");
+        assert_eq!(
+            language(Path::new(
+                "/site/i18n/fr/docusaurus-plugin-content-docs/current/API/a.md"
+            )),
+            "fr"
+        );
+    }
+
+    #[test]
+    fn index_both_ways() {
+        let image = PathBuf::from("/site/docs/assets/en/a.png");
+        let unused = PathBuf::from("/site/docs/assets/en/b.png");
+        let page = PathBuf::from("/site/i18n/ja/x/current/a.md");
+        let files_map = BTreeSet::from([image.clone(), unused.clone()]);
+        let images_used_set = BTreeMap::from([(image.clone(), BTreeSet::from([page.clone()]))]);
+
+        let index = usage_index(&files_map, &images_used_set);
+        assert_eq!(index.images[0].pages[0].language, "ja");
+        assert!(index.images[1].pages.is_empty());
+        assert_eq!(index.pages[0].images, vec![image]);
+    }
+}
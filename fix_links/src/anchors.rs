@@ -0,0 +1,157 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The anchor of a heading, as github-slugger computes it for Docusaurus: lowercase, the
+/// punctuation removed and the spaces replaced by `-`
+pub fn slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            _ if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Follows the fenced code blocks of a Markdown document, line by line
+#[derive(Default)]
+pub struct Fence {
+    open: Option<&'static str>,
+}
+
+impl Fence {
+    /// Whether the line opens, closes or is inside a fenced code block
+    pub fn is_code(&mut self, line: &str) -> bool {
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            self.open = match self.open {
+                Some(open) if open == marker => None,
+                None => Some(marker),
+                open => open,
+            };
+            return true;
+        }
+        self.open.is_some()
+    }
+}
+
+/// The anchors of the Markdown files, read once per file
+pub struct AnchorIndex {
+    heading: Regex,
+    custom_id: Regex,
+    html: Regex,
+    formatting: Regex,
+    files: HashMap<PathBuf, Vec<String>>,
+}
+
+impl AnchorIndex {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            heading: Regex::new(r"^ {0,3}#{1,6}[ \t]+(?P<text>.*?)(?:[ \t]+#+)?[ \t]*$")?,
+            custom_id: Regex::new(r"\s*\{#(?P<id>[^}\s]+)\}$")?,
+            html: Regex::new(r#"<a\s[^>]*?(?:name|id)\s*=\s*["'](?P<id>[^"']+)["']"#)?,
+            // [text](link), <tag>, `code`, **bold**, ~~strike~~
+            formatting: Regex::new(r"!?\[(?P<text>[^\]]*)\]\([^)]*\)|<[^>]+>|[`*~]")?,
+            files: HashMap::new(),
+        })
+    }
+
+    /// The anchors of a Markdown document, in document order
+    pub fn parse(&self, content: &str) -> Vec<String> {
+        let mut anchors = Vec::new();
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut fence = Fence::default();
+        for line in content.lines() {
+            if fence.is_code(line) {
+                continue;
+            }
+            anchors.extend(
+                self.html
+                    .captures_iter(line)
+                    .map(|caps| caps["id"].to_string()),
+            );
+            let Some(caps) = self.heading.captures(line) else {
+                continue;
+            };
+            let text = &caps["text"];
+            if let Some(custom) = self.custom_id.captures(text) {
+                anchors.push(custom["id"].to_string());
+                continue;
+            }
+            let text = self.formatting.replace_all(text, "$text");
+            // the same heading twice gets `-1`, `-2`...
+            let base = slug(&text);
+            let count = occurrences.entry(base.clone()).or_default();
+            anchors.push(match *count {
+                0 => base.clone(),
+                n => format!("{}-{}", base, n),
+            });
+            *count += 1;
+        }
+        anchors
+    }
+
+    /// The anchors of a file, empty if it cannot be read
+    pub fn anchors(&mut self, path: &Path) -> &[String] {
+        if !self.files.contains_key(path) {
+            let anchors = fs::read_to_string(path)
+                .map(|content| self.parse(&content))
+                .unwrap_or_default();
+            self.files.insert(path.to_path_buf(), anchors);
+        }
+        &self.files[path]
+    }
+}
+
+/// The anchor most similar to a missing one
+pub fn closest<'a>(anchor: &str, anchors: &'a [String]) -> Option<&'a String> {
+    anchors
+        .iter()
+        .map(|candidate| (strsim::normalized_levenshtein(anchor, candidate), candidate))
+        .filter(|(similarity, _)| *similarity >= 0.5)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slug("Collection.copy()"), "collectioncopy");
+        assert_eq!(slug("New in 4D v20 R5!"), "new-in-4d-v20-r5");
+        assert_eq!(slug("Références"), "références");
+        assert_eq!(slug("snake_case and-dash"), "snake_case-and-dash");
+    }
+
+    #[test]
+    fn headings() {
+        let index = AnchorIndex::new().unwrap();
+        let content = "# Title\n\n## `.copy()`\n\n```4d\n# not a heading\n```\n\n## Example\n### Example\n## Custom {#my-id}\n## [Link](a.md) **bold** ##\n<a name=\"old-name\"></a>\n";
+        assert_eq!(
+            index.parse(content),
+            vec![
+                "title",
+                "copy",
+                "example",
+                "example-1",
+                "my-id",
+                "link-bold",
+                "old-name"
+            ]
+        );
+    }
+
+    #[test]
+    fn closest_anchor() {
+        let anchors = vec!["copy".to_string(), "distinct".to_string()];
+        assert_eq!(closest("copie", &anchors), Some(&anchors[0]));
+        assert_eq!(closest("query", &anchors), None);
+    }
+}
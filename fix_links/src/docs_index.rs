@@ -0,0 +1,140 @@
+use glob::glob;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The `key: value` pairs of the front matter of a Markdown document
+pub fn front_matter(content: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut lines = content.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return values;
+    }
+    for line in lines {
+        if line.trim_end() == "---" {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches(['"', '\'']);
+            values.insert(key.trim().to_string(), value.to_string());
+        }
+    }
+    values
+}
+
+/// The docs of a docs root by published path, e.g. `ORDA/overview` for `ORDA/ordaOverview.md`
+/// with `slug: overview` in its front matter
+#[derive(Default, Debug)]
+pub struct DocIndex {
+    by_url: HashMap<String, String>,
+}
+
+impl DocIndex {
+    pub fn build(root: &Path) -> Result<Self, anyhow::Error> {
+        let mut index = DocIndex::default();
+        // glob drops the `./` of `./docs`, the files are globbed from the canonical root
+        let root = root.canonicalize()?;
+        for extension in ["md", "mdx"] {
+            for entry in glob(&format!("{}/**/*.{}", root.display(), extension))? {
+                let path = entry?;
+                index.insert(path.strip_prefix(&root)?, &fs::read_to_string(&path)?);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Adds a doc, `relative` to the docs root
+    pub fn insert(&mut self, relative: &Path, content: &str) {
+        let file = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let directory = relative
+            .parent()
+            .map(|parent| {
+                parent
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default();
+        let join = |name: &str| match directory.as_str() {
+            "" => name.to_string(),
+            _ if name.is_empty() => directory.clone(),
+            _ => format!("{}/{}", directory, name),
+        };
+
+        let values = front_matter(content);
+        let stem = relative
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let url = match (values.get("slug"), values.get("id")) {
+            (Some(slug), _) if slug.starts_with('/') => slug.trim_matches('/').to_string(),
+            (Some(slug), _) => join(slug.trim_end_matches('/')),
+            // the index of a folder is served at the folder
+            (None, None) if ["index", "README"].contains(&stem.as_str()) => join(""),
+            (None, Some(id)) => join(id),
+            (None, None) => join(&stem),
+        };
+        self.by_url.entry(url).or_insert(file);
+    }
+
+    /// The file of a published path, relative to the docs root
+    pub fn resolve(&self, url: &str) -> Option<&String> {
+        self.by_url.get(url.trim_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn front_matter_values() {
+        let values = front_matter("---\nid: ordaOverview\ntitle: \"Overview: ORDA\"\n---\n# x");
+        assert_eq!(values["id"], "ordaOverview");
+        assert_eq!(values["title"], "Overview: ORDA");
+        assert!(front_matter("# no front matter").is_empty());
+    }
+
+    #[test]
+    fn published_paths() {
+        let mut index = DocIndex::default();
+        index.insert(
+            Path::new("ORDA/ordaOverview.md"),
+            "---\nid: ordaOverview\nslug: overview\n---\n",
+        );
+        index.insert(
+            Path::new("API/CollectionClass.md"),
+            "---\nid: Collection\n---\n",
+        );
+        index.insert(Path::new("Concepts/about.md"), "---\nslug: /about\n---\n");
+        index.insert(Path::new("Project/index.md"), "# Project");
+        index.insert(Path::new("ORDA/entities.md"), "# Entities");
+
+        assert_eq!(
+            index.resolve("ORDA/overview").map(String::as_str),
+            Some("ORDA/ordaOverview.md")
+        );
+        assert_eq!(
+            index.resolve("API/Collection").map(String::as_str),
+            Some("API/CollectionClass.md")
+        );
+        assert_eq!(
+            index.resolve("about/").map(String::as_str),
+            Some("Concepts/about.md")
+        );
+        assert_eq!(
+            index.resolve("Project").map(String::as_str),
+            Some("Project/index.md")
+        );
+        assert_eq!(
+            index.resolve("ORDA/entities").map(String::as_str),
+            Some("ORDA/entities.md")
+        );
+        assert_eq!(index.resolve("ORDA/missing"), None);
+    }
+}
@@ -1,7 +1,14 @@
+mod anchors;
+mod docs_index;
+mod validate;
+mod versions;
+
 use clap::Parser;
 use glob::glob;
 use regex::Regex;
-use std::{fs, path};
+use std::fs;
+use std::path::Path;
+use versions::{LanguagePolicy, Links, Policy};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -16,22 +23,71 @@ struct Args {
 
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
+
+    /// Report the relative links to Markdown files that do not exist and fail if any
+    #[arg(short, long, default_value_t = false)]
+    check: bool,
+
+    /// The links to another version than the one of the page
+    #[arg(long, value_enum, default_value_t = Policy::Keep)]
+    cross_version: Policy,
+
+    /// The links to another language than the one of the page
+    #[arg(long, value_enum, default_value_t = LanguagePolicy::Keep)]
+    cross_language: LanguagePolicy,
+
+    /// The version published without version segment, e.g. `21`, the `docs` folder otherwise
+    #[arg(long)]
+    last_version: Option<String>,
 }
 
-fn link_modifier(in_link: &str) -> Result<Option<String>, anyhow::Error> {
+/// The relative link from a folder to the docs root, e.g. `../../` for `ORDA/sub`
+fn root_prefix(relative_dir: &Path) -> String {
+    match relative_dir.components().count() {
+        0 => "./".to_string(),
+        depth => "../".repeat(depth),
+    }
+}
+
+/// Rewrites a link to the published docs into a link relative to the page, `root_prefix` being
+/// the relative link from the folder of the page to the docs root
+fn link_modifier(
+    in_link: &str,
+    root_prefix: &str,
+    links: &mut Links,
+) -> Result<Option<String>, anyhow::Error> {
     let mut link = in_link.to_string();
     let is_doc_link = link.starts_with("https://developer.4d.com/docs") || link.starts_with("../");
     if link.starts_with("https://developer.4d.com/docs")
     {
         link = urlencoding::decode(&link)?.to_string();
-        let regex = Regex::new(r#"https://developer\.4d\.com/docs/(([0-9]{2}(R[0-9]+)?)|(en|fr|pt|ja|es))?/?(.*)"#)?;
         link = link.replace("/#", "#");
-        link = regex.replace(&link, "../$5").to_string();
+        let Some(url) = versions::parse_url(&link, links.last_version.as_deref()) else {
+            return Ok(None);
+        };
+        // a link to another version or language is kept absolute by policy, or when its tree
+        // is not in the site
+        let Some(tree) = links.target_tree(&url.tree) else {
+            return Ok(None);
+        };
+        let Some((tree_prefix, index)) = links.tree(&tree)? else {
+            return Ok(None);
+        };
+        let root_prefix = match root_prefix {
+            "./" if !tree_prefix.is_empty() => tree_prefix.to_string(),
+            _ => format!("{}{}", root_prefix, tree_prefix),
+        };
+        let root_prefix = root_prefix.as_str();
+        link = format!("{}{}", root_prefix, url.path);
         if is_doc_link && link.ends_with("/") {
             link.pop();
         }
         let mut link_modified = link.clone();
-        if !link.contains(".md")
+        let (path, fragment) = link.split_at(link.find('#').unwrap_or(link.len()));
+        // the published path may be a slug or an id differing from the file name
+        if let Some(file) = path.strip_prefix(root_prefix).and_then(|url| index.resolve(url)) {
+            link_modified = format!("{}{}{}", root_prefix, file, fragment);
+        } else if !link.contains(".md")
         {
             link_modified = link.find("#").map_or_else(|| {
                 let mut temp_link = link.clone();
@@ -58,7 +114,12 @@ fn link_modifier(in_link: &str) -> Result<Option<String>, anyhow::Error> {
     Ok(None)
 }
 
-fn fix_links(new_content: &mut String, regex: &Regex) -> Result<bool, anyhow::Error> {
+fn fix_links(
+    new_content: &mut String,
+    regex: &Regex,
+    root_prefix: &str,
+    links: &mut Links,
+) -> Result<bool, anyhow::Error> {
     let mut replacements = Vec::new();
     let mut has_changed = false;
     let mut start = 0;
@@ -66,7 +127,7 @@ fn fix_links(new_content: &mut String, regex: &Regex) -> Result<bool, anyhow::Er
     while let Some(caps) = regex.captures(&new_content[start..]) {
         let full_match = caps.get(1).unwrap();
         let link = caps.get(1).map(|m| m.as_str()).unwrap();
-        if let Some(link_modified) = link_modifier(link)? {
+        if let Some(link_modified) = link_modifier(link, root_prefix, links)? {
             println!("Link: {} {}", link, link_modified);
 
             replacements.push((
@@ -93,18 +154,143 @@ fn main() -> Result<(), anyhow::Error> {
 
     //regex to find markdown links
     let regex: Regex = Regex::new(r#"\[.*?\]\(([^ \)]*/.*?)( "(.+)")?\)"#)?;
+    let extractor = validate::LinkExtractor::new()?;
+    let mut anchors = anchors::AnchorIndex::new()?;
+    let mut dangling = 0;
 
     for directory in args.paths {
-        for entry in glob(format!("{}/**/*.md", directory.as_str()).as_str())? {
+        // glob drops the `./` of `-p ./docs`, the pages are located from the canonical root
+        let root = Path::new(&directory).canonicalize()?;
+        // the trees are detected from the canonical root, e.g. for `-p .` inside `docs/`
+        let mut links = Links::new(
+            &root,
+            args.cross_version,
+            args.cross_language,
+            args.last_version.clone(),
+        )?;
+        for entry in glob(format!("{}/**/*.md", directory.as_str()).as_str())?
+            .chain(glob(format!("{}/**/*.mdx", directory.as_str()).as_str())?)
+        {
             let path = entry?;
             let content = fs::read_to_string(path.as_path())?;
             let mut new_content = content.clone();
-            let has_changed = fix_links(&mut new_content, &regex)?;
+            let page = path.canonicalize()?;
+            let relative_dir = page
+                .parent()
+                .and_then(|parent| parent.strip_prefix(&root).ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("{} is not under {}", path.display(), root.display())
+                })?;
+            let has_changed = fix_links(&mut new_content, &regex, &root_prefix(relative_dir), &mut links)?;
+
+            if args.check {
+                // the links are checked as rewritten by link_modifier
+                for link in validate::dangling_links(&path, &new_content, &extractor, &mut anchors) {
+                    match (&link.anchor, &link.suggestion) {
+                        (None, _) => println!("{}: broken link {}", link.location(), link.link),
+                        (Some(anchor), Some(suggestion)) => println!(
+                            "{}: missing anchor #{} in {}, did you mean #{}?",
+                            link.location(),
+                            anchor,
+                            link.link,
+                            suggestion
+                        ),
+                        (Some(anchor), None) => println!(
+                            "{}: missing anchor #{} in {}",
+                            link.location(),
+                            anchor,
+                            link.link
+                        ),
+                    }
+                    dangling += 1;
+                }
+            }
 
             if args.fix && has_changed {
                 fs::write(path.as_path(), new_content)?;
             }
         }
     }
+
+    if dangling > 0 {
+        anyhow::bail!("{} broken link(s)", dangling);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use docs_index::DocIndex;
+    use versions::Tree;
+
+    fn links(index: DocIndex) -> Links {
+        Links::with_index(
+            Tree {
+                language: "en".to_string(),
+                version: "current".to_string(),
+            },
+            index,
+            Policy::Keep,
+            LanguagePolicy::Keep,
+        )
+    }
+
+    #[test]
+    fn root_prefixes() {
+        assert_eq!(root_prefix(Path::new("")), "./");
+        assert_eq!(root_prefix(Path::new("API")), "../");
+        assert_eq!(root_prefix(Path::new("ORDA/sub")), "../../");
+    }
+
+    #[test]
+    fn links_at_any_depth() {
+        let link = "https://developer.4d.com/docs/API/CollectionClass#copy";
+        assert_eq!(
+            link_modifier(link, "../", &mut links(DocIndex::default())).unwrap(),
+            Some("../API/CollectionClass.md#copy".to_string())
+        );
+        assert_eq!(
+            link_modifier(link, "../../", &mut links(DocIndex::default())).unwrap(),
+            Some("../../API/CollectionClass.md#copy".to_string())
+        );
+        assert_eq!(
+            link_modifier(link, "./", &mut links(DocIndex::default())).unwrap(),
+            Some("./API/CollectionClass.md#copy".to_string())
+        );
+    }
+
+    #[test]
+    fn links_to_slugs() {
+        let mut index = DocIndex::default();
+        index.insert(
+            Path::new("ORDA/ordaOverview.md"),
+            "---\nid: ordaOverview\nslug: overview\n---\n",
+        );
+        assert_eq!(
+            link_modifier(
+                "https://developer.4d.com/docs/ORDA/overview/#what-is-orda",
+                "../",
+                &mut links(index)
+            )
+            .unwrap(),
+            Some("../ORDA/ordaOverview.md#what-is-orda".to_string())
+        );
+    }
+
+    #[test]
+    fn links_to_other_versions() {
+        let mut links = links(DocIndex::default());
+        let link = "https://developer.4d.com/docs/fr/20R5/API/CollectionClass";
+        assert_eq!(link_modifier(link, "../", &mut links).unwrap(), None);
+        links.cross_version = Policy::Collapse;
+        links.cross_language = LanguagePolicy::Collapse;
+        assert_eq!(
+            link_modifier(link, "../", &mut links).unwrap(),
+            Some("../API/CollectionClass.md".to_string())
+        );
+        // the tree of the version is not in the site
+        links.cross_version = Policy::Map;
+        assert_eq!(link_modifier(link, "../", &mut links).unwrap(), None);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::anchors::{self, AnchorIndex, Fence};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// A relative link to a Markdown file or to a heading that does not exist
+#[derive(Debug, PartialEq)]
+pub struct DanglingLink {
+    pub source: PathBuf,
+    pub line: usize,
+    pub link: String,
+    /// The missing anchor, the file exists
+    pub anchor: Option<String>,
+    /// The closest anchor of the file
+    pub suggestion: Option<String>,
+}
+
+impl DanglingLink {
+    /// `file.md:line`, as in a compiler diagnostic
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.source.display(), self.line)
+    }
+}
+
+/// Finds the destinations of the Markdown links, with their byte offset
+pub struct LinkExtractor {
+    regex: Regex,
+}
+
+impl LinkExtractor {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            regex: Regex::new(r#"\]\(\s*<?(?P<link>[^\s)>]+)"#)?,
+        })
+    }
+
+    /// The links outside of the inline code spans
+    pub fn extract<'a>(&self, content: &'a str) -> Vec<(usize, &'a str)> {
+        self.regex
+            .captures_iter(&blank_code_spans(content))
+            .filter_map(|caps| caps.name("link"))
+            .map(|link| (link.start(), &content[link.range()]))
+            .collect()
+    }
+}
+
+/// The content with the inline code spans replaced by spaces, the offsets unchanged
+fn blank_code_spans(content: &str) -> String {
+    let mut blanked = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(open) = rest.find('`') {
+        let ticks = rest[open..].len() - rest[open..].trim_start_matches('`').len();
+        let after = &rest[open + ticks..];
+        // the span ends at a run of as many backticks
+        let mut close = None;
+        let mut searched = 0;
+        while let Some(found) = after[searched..].find('`') {
+            let start = searched + found;
+            let run = after[start..].len() - after[start..].trim_start_matches('`').len();
+            if run == ticks {
+                close = Some(start);
+                break;
+            }
+            searched = start + run;
+        }
+        let Some(close) = close else {
+            blanked.push_str(&rest[..open + ticks]);
+            rest = after;
+            continue;
+        };
+        blanked.push_str(&rest[..open + ticks]);
+        for c in after[..close].chars() {
+            match c {
+                '\n' => blanked.push('\n'),
+                _ => blanked.extend(std::iter::repeat_n(' ', c.len_utf8())),
+            }
+        }
+        blanked.push_str(&after[close..close + ticks]);
+        rest = &after[close + ticks..];
+    }
+    blanked.push_str(rest);
+    blanked
+}
+
+/// The Markdown file a relative link points to, without its fragment
+pub fn markdown_target(link: &str) -> Option<String> {
+    if link.contains("://") || link.starts_with("mailto:") || link.starts_with('/') {
+        return None;
+    }
+    let path = link.split(['#', '?']).next().unwrap_or_default();
+    let path = urlencoding::decode(path).map_or(path.to_string(), |path| path.into_owned());
+    (path.ends_with(".md") || path.ends_with(".mdx")).then_some(path)
+}
+
+/// The decoded fragment of a link, e.g. `copy` for `../API/CollectionClass.md#copy`
+pub fn fragment(link: &str) -> Option<String> {
+    let (_, fragment) = link.split_once('#')?;
+    let fragment = urlencoding::decode(fragment).map_or(fragment.to_string(), |f| f.into_owned());
+    (!fragment.is_empty()).then_some(fragment)
+}
+
+/// 1-based line of a byte offset
+pub fn line(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// The links of a page whose Markdown target or heading does not exist, the links to a heading
+/// of the page itself included. The code blocks are skipped.
+pub fn dangling_links(
+    path: &Path,
+    content: &str,
+    extractor: &LinkExtractor,
+    anchors: &mut AnchorIndex,
+) -> Vec<DanglingLink> {
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut fence = Fence::default();
+    let code: Vec<bool> = content.lines().map(|line| fence.is_code(line)).collect();
+    let mut dangling = Vec::new();
+    for (offset, link) in extractor.extract(content) {
+        let line = line(content, offset);
+        if code.get(line - 1) == Some(&true) {
+            continue;
+        }
+        let target = match markdown_target(link) {
+            Some(target) => directory.join(target),
+            None if link.starts_with('#') => path.to_path_buf(),
+            None => continue,
+        };
+        let mut broken = DanglingLink {
+            source: path.to_path_buf(),
+            line,
+            link: link.to_string(),
+            anchor: None,
+            suggestion: None,
+        };
+        if !target.is_file() {
+            dangling.push(broken);
+            continue;
+        }
+        let Some(fragment) = fragment(link) else {
+            continue;
+        };
+        let existing = anchors.anchors(&target);
+        if !existing.contains(&fragment) {
+            broken.suggestion = anchors::closest(&fragment, existing).cloned();
+            broken.anchor = Some(fragment);
+            dangling.push(broken);
+        }
+    }
+    dangling
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn targets() {
+        assert_eq!(
+            markdown_target("../API/CollectionClass.md#copy"),
+            Some("../API/CollectionClass.md".to_string())
+        );
+        assert_eq!(
+            markdown_target("My%20Page.md"),
+            Some("My Page.md".to_string())
+        );
+        assert_eq!(markdown_target("https://developer.4d.com/docs/a.md"), None);
+        assert_eq!(markdown_target("../assets/en/a.png"), None);
+    }
+
+    #[test]
+    fn dangling() {
+        let temp = tempfile::tempdir().unwrap();
+        let directory = temp.path();
+        fs::create_dir_all(directory.join("API")).unwrap();
+        let page = directory.join("API/page.md");
+        fs::write(directory.join("API/CollectionClass.md"), "## .copy()\n").unwrap();
+        let content = "# Page\n[a](CollectionClass.md#copy)\n[b](../ORDA/missing.md)\n[c](https://4d.com)\n[d](CollectionClass.md#copie)\n[e](#page)\n[f](#pages)\n```md\n[g](../x.md)\n```\n`[h](../../nope.md)` and ``[i](`../nope.md`)``";
+        fs::write(&page, content).unwrap();
+
+        let links = dangling_links(
+            &page,
+            content,
+            &LinkExtractor::new().unwrap(),
+            &mut AnchorIndex::new().unwrap(),
+        );
+        assert_eq!(
+            links,
+            vec![
+                DanglingLink {
+                    source: page.clone(),
+                    line: 3,
+                    link: "../ORDA/missing.md".to_string(),
+                    anchor: None,
+                    suggestion: None,
+                },
+                DanglingLink {
+                    source: page.clone(),
+                    line: 5,
+                    link: "CollectionClass.md#copie".to_string(),
+                    anchor: Some("copie".to_string()),
+                    suggestion: Some("copy".to_string()),
+                },
+                DanglingLink {
+                    source: page.clone(),
+                    line: 7,
+                    link: "#pages".to_string(),
+                    anchor: Some("pages".to_string()),
+                    suggestion: Some("page".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use crate::docs_index::DocIndex;
+use clap::ValueEnum;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// What to do with a link to another version than the one of the page
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Policy {
+    /// Keep the absolute URL
+    Keep,
+    /// Link to the file in the tree of the other version
+    Map,
+    /// Link to the file in the tree of the page, as if the segment was not there
+    Collapse,
+}
+
+/// What to do with a link to another language than the one of the page. There is no `map`:
+/// Docusaurus does not resolve file links from a locale to another.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum LanguagePolicy {
+    /// Keep the absolute URL
+    Keep,
+    /// Link to the file in the tree of the page, as if the segment was not there
+    Collapse,
+}
+
+/// A docs tree: a language and a version, `current` for the `docs` folder
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tree {
+    pub language: String,
+    pub version: String,
+}
+
+impl Tree {
+    /// The tree of a docs root, e.g. `fr` and `20R5` for
+    /// `i18n/fr/docusaurus-plugin-content-docs/version-20R5/`
+    pub fn of_root(root: &Path) -> Tree {
+        let names: Vec<String> = root
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let version = |name: &str| {
+            name.strip_prefix("version-")
+                .unwrap_or("current")
+                .to_string()
+        };
+        let mut tree = Tree {
+            language: "en".to_string(),
+            version: "current".to_string(),
+        };
+        for (i, name) in names.iter().enumerate() {
+            match (name.as_str(), names.get(i + 1), names.get(i + 3)) {
+                ("versioned_docs", Some(folder), _) => tree.version = version(folder),
+                ("i18n", Some(language), Some(folder)) => {
+                    tree.language = language.clone();
+                    tree.version = version(folder);
+                }
+                _ => {}
+            }
+        }
+        tree
+    }
+
+    /// The docs root of the tree, relative to the site root
+    pub fn root(&self) -> PathBuf {
+        let folder = match self.version.as_str() {
+            "current" => "current".to_string(),
+            version => format!("version-{}", version),
+        };
+        match (self.language.as_str(), self.version.as_str()) {
+            ("en", "current") => PathBuf::from("docs"),
+            ("en", _) => Path::new("versioned_docs").join(folder),
+            (language, _) => Path::new("i18n")
+                .join(language)
+                .join("docusaurus-plugin-content-docs")
+                .join(folder),
+        }
+    }
+}
+
+/// A link to the published docs: its tree and the path in the tree, with its fragment
+#[derive(Debug, PartialEq)]
+pub struct DocUrl {
+    pub tree: Tree,
+    pub path: String,
+}
+
+/// Splits the language and version segments of a docs URL, in this order as published:
+/// `https://developer.4d.com/docs/fr/20R5/API/CollectionClass`. `next` is the `docs` folder and a
+/// URL without version is `last_version` if given.
+pub fn parse_url(link: &str, last_version: Option<&str>) -> Option<DocUrl> {
+    let regex = Regex::new(
+        r"^https://developer\.4d\.com/docs/?(?:(?P<language>en|fr|pt|ja|es)(?:/|$))?(?:(?P<version>[0-9]{2}(?:R[0-9]+)?|next)(?:/|$))?(?P<path>.*)$",
+    )
+    .ok()?;
+    let caps = regex.captures(link)?;
+    let version = match caps.name("version").map(|version| version.as_str()) {
+        Some("next") => "current",
+        Some(version) => version,
+        None => last_version.unwrap_or("current"),
+    };
+    Some(DocUrl {
+        tree: Tree {
+            language: caps
+                .name("language")
+                .map_or("en", |language| language.as_str())
+                .to_string(),
+            version: version.to_string(),
+        },
+        path: caps["path"].to_string(),
+    })
+}
+
+/// The docs trees the links of the pages of a docs root point to
+pub struct Links {
+    pub tree: Tree,
+    /// The site root, if the docs root is at its place in the site
+    site_root: Option<PathBuf>,
+    pub cross_version: Policy,
+    pub cross_language: LanguagePolicy,
+    pub last_version: Option<String>,
+    /// The link from the docs root to the root of each tree, with its index
+    indexes: HashMap<Tree, Option<(String, DocIndex)>>,
+}
+
+impl Links {
+    pub fn new(
+        root: &Path,
+        cross_version: Policy,
+        cross_language: LanguagePolicy,
+        last_version: Option<String>,
+    ) -> Result<Self, anyhow::Error> {
+        let tree = Tree::of_root(root);
+        let index = DocIndex::build(root)?;
+        let mut links = Self::with_index(tree, index, cross_version, cross_language);
+        links.last_version = last_version;
+        // the root ends with the place of the tree in the site
+        let depth = links.tree.root().components().count();
+        let components: Vec<Component> = root.components().collect();
+        if components.len() >= depth
+            && components[components.len() - depth..]
+                .iter()
+                .map(|component| component.as_os_str())
+                .eq(links.tree.root().iter())
+        {
+            links.site_root = Some(components[..components.len() - depth].iter().collect());
+        }
+        Ok(links)
+    }
+
+    pub fn with_index(
+        tree: Tree,
+        index: DocIndex,
+        cross_version: Policy,
+        cross_language: LanguagePolicy,
+    ) -> Self {
+        let mut indexes = HashMap::new();
+        indexes.insert(tree.clone(), Some((String::new(), index)));
+        Self {
+            tree,
+            site_root: None,
+            cross_version,
+            cross_language,
+            last_version: None,
+            indexes,
+        }
+    }
+
+    /// The tree a link is rewritten to, `None` to keep the URL
+    pub fn target_tree(&self, linked: &Tree) -> Option<Tree> {
+        let language = match self.cross_language {
+            _ if self.tree.language == linked.language => &linked.language,
+            LanguagePolicy::Keep => return None,
+            LanguagePolicy::Collapse => &self.tree.language,
+        };
+        let version = match self.cross_version {
+            _ if self.tree.version == linked.version => &linked.version,
+            Policy::Keep => return None,
+            Policy::Map => &linked.version,
+            Policy::Collapse => &self.tree.version,
+        };
+        Some(Tree {
+            language: language.clone(),
+            version: version.clone(),
+        })
+    }
+
+    /// The link from the docs root to the root of the tree, e.g. `../versioned_docs/version-20/`
+    /// from `docs/`, with the index of the tree. `None` if the tree is not in the site.
+    pub fn tree(&mut self, tree: &Tree) -> Result<Option<(&str, &DocIndex)>, anyhow::Error> {
+        if !self.indexes.contains_key(tree) {
+            let mut found = None;
+            if let Some(site_root) = &self.site_root {
+                let root = site_root.join(tree.root());
+                if root.is_dir() {
+                    let prefix = format!(
+                        "{}{}/",
+                        "../".repeat(self.tree.root().components().count()),
+                        tree.root()
+                            .components()
+                            .map(|component| component.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/")
+                    );
+                    found = Some((prefix, DocIndex::build(&root)?));
+                }
+            }
+            self.indexes.insert(tree.clone(), found);
+        }
+        Ok(self.indexes[tree]
+            .as_ref()
+            .map(|(prefix, index)| (prefix.as_str(), index)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(language: &str, version: &str) -> Tree {
+        Tree {
+            language: language.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn trees_of_roots() {
+        assert_eq!(
+            Tree::of_root(Path::new("../../docs/docs/")),
+            tree("en", "current")
+        );
+        assert_eq!(
+            Tree::of_root(Path::new("site/versioned_docs/version-20R5")),
+            tree("en", "20R5")
+        );
+        let french = Tree::of_root(Path::new(
+            "site/i18n/fr/docusaurus-plugin-content-docs/version-20",
+        ));
+        assert_eq!(french, tree("fr", "20"));
+        assert_eq!(
+            french.root(),
+            PathBuf::from("i18n/fr/docusaurus-plugin-content-docs/version-20")
+        );
+    }
+
+    #[test]
+    fn urls() {
+        let parse = |link| parse_url(link, None).unwrap();
+        assert_eq!(
+            parse("https://developer.4d.com/docs/fr/20R5/API/CollectionClass#copy"),
+            DocUrl {
+                tree: tree("fr", "20R5"),
+                path: "API/CollectionClass#copy".to_string()
+            }
+        );
+        assert_eq!(
+            parse("https://developer.4d.com/docs/20/ORDA/b").tree,
+            tree("en", "20")
+        );
+        assert_eq!(
+            parse("https://developer.4d.com/docs/next/ORDA/b").tree,
+            tree("en", "current")
+        );
+        assert_eq!(
+            parse("https://developer.4d.com/docs/ja/ORDA/b").tree,
+            tree("ja", "current")
+        );
+        assert_eq!(
+            parse_url("https://developer.4d.com/docs/ORDA/b", Some("21"))
+                .unwrap()
+                .tree,
+            tree("en", "21")
+        );
+        // a folder named like a language is not one
+        assert_eq!(
+            parse("https://developer.4d.com/docs/english/a").path,
+            "english/a"
+        );
+    }
+
+    #[test]
+    fn policies() {
+        let links = |cross_version, cross_language| {
+            Links::with_index(
+                tree("en", "current"),
+                DocIndex::default(),
+                cross_version,
+                cross_language,
+            )
+        };
+        let linked = tree("fr", "20");
+        assert_eq!(
+            links(Policy::Keep, LanguagePolicy::Collapse).target_tree(&linked),
+            None
+        );
+        assert_eq!(
+            links(Policy::Map, LanguagePolicy::Collapse).target_tree(&linked),
+            Some(tree("en", "20"))
+        );
+        assert_eq!(
+            links(Policy::Keep, LanguagePolicy::Keep).target_tree(&tree("en", "current")),
+            Some(tree("en", "current"))
+        );
+    }
+
+    #[test]
+    fn mapped_tree() {
+        let temp = tempfile::tempdir().unwrap();
+        let site = temp.path();
+        std::fs::create_dir_all(site.join("docs/API")).unwrap();
+        std::fs::create_dir_all(site.join("versioned_docs/version-20/API")).unwrap();
+
+        let mut links =
+            Links::new(&site.join("docs"), Policy::Map, LanguagePolicy::Keep, None).unwrap();
+        let (prefix, _) = links.tree(&tree("en", "20")).unwrap().unwrap();
+        assert_eq!(prefix, "../versioned_docs/version-20/");
+        assert!(links.tree(&tree("en", "19")).unwrap().is_none());
+    }
+}
@@ -26,8 +26,7 @@ impl Param {
         Self {
             name: name.map(|s| s.to_string()),
             param: param.map(|s| {
-                s.to_string()
-                    .split(",")
+                s.split(",")
                     .map(|s| s.trim().to_string())
                     .collect()
             }),
@@ -250,10 +249,10 @@ fn check_syntax(
 
         //Check only english, types are translated in other languages
         let str_path = path.to_str().unwrap_or("");
-        if str_path.contains("i18n/en/") || !str_path.contains("i18n") {
-            if !check_params_array(&params, &logger) {
-                logger.print_complementary_info();
-            }
+        if (str_path.contains("i18n/en/") || !str_path.contains("i18n"))
+            && !check_params_array(&params, &logger)
+        {
+            logger.print_complementary_info();
         }
 
 